@@ -1,6 +1,165 @@
-use crate::{Notification, WebhookPayload};
+use serde::{Deserialize, Serialize};
 
+use crate::{Notification, WebhookPayload, utils::format_duration};
+
+const KNOWN_EVENTS: &[&str] = &[
+    "docker_cleanup_success",
+    "docker_cleanup_failed",
+    "backup_success",
+    "backup_failed",
+    "backup_success_with_s3_warning",
+    "server_patches_available",
+    "server_patch_check",
+    "server_patch_check_error",
+    "server_reachable",
+    "server_unreachable",
+    "high_disk_usage",
+    "deployment_started",
+    "deployment_success",
+    "deployment_failed",
+    "container_stopped",
+    "container_restarted",
+    "status_changed",
+    "traefik_version_outdated",
+    "task_success",
+    "task_failed",
+    "coolify_api_unreachable",
+    "coolify_api_reachable",
+    "test",
+];
+
+pub fn is_known_event(event: &str) -> bool {
+    KNOWN_EVENTS.contains(&event)
+}
+
+/// True when a webhook has no notification of its own and is shown as a
+/// generic `Event: <name>` message: unknown events, and `status_changed`
+/// events other than a stopped application.
+pub fn is_fallback(payload: &WebhookPayload) -> bool {
+    match payload.event.as_deref() {
+        Some("status_changed") => !is_application_stopped(payload),
+        Some(event) => !is_known_event(event),
+        None => true,
+    }
+}
+
+/// Broad outcome of an event, used by chat outputs to pick colours and icons.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventFamily {
+    Success,
+    Failure,
+    Info,
+}
+
+pub fn event_family(event: &str) -> EventFamily {
+    match event {
+        "server_reachable" | "container_restarted" | "coolify_api_reachable" => {
+            EventFamily::Success
+        }
+        "server_unreachable"
+        | "high_disk_usage"
+        | "container_stopped"
+        | "coolify_api_unreachable" => EventFamily::Failure,
+        event if event.ends_with("_success") => EventFamily::Success,
+        event if event.ends_with("_failed") || event.ends_with("_error") => EventFamily::Failure,
+        _ => EventFamily::Info,
+    }
+}
+
+/// How urgently an event needs attention.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+pub fn event_severity(event: &str) -> EventSeverity {
+    match event {
+        "server_unreachable" | "backup_failed" | "deployment_failed" | "high_disk_usage"
+        | "container_stopped" => EventSeverity::Critical,
+        "backup_success_with_s3_warning"
+        | "server_patches_available"
+        | "traefik_version_outdated" => EventSeverity::Warning,
+        event if event_family(event) == EventFamily::Failure => EventSeverity::Warning,
+        _ => EventSeverity::Info,
+    }
+}
+
+/// A link to a Coolify resource mentioned in a webhook.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub label: &'static str,
+    pub url: String,
+}
+
+/// Links found in the payload, most specific first. Domains without a scheme
+/// are assumed to be served over HTTPS.
+pub fn links(payload: &WebhookPayload) -> Vec<Link> {
+    let candidates = [
+        ("Open in Coolify", payload.url.as_deref()),
+        ("View deployment", payload.deployment_url.as_deref()),
+        ("Open preview", payload.preview_fqdn.as_deref()),
+        ("Open application", payload.fqdn.as_deref()),
+    ];
+
+    let mut links: Vec<Link> = Vec::new();
+    for (label, value) in candidates {
+        // Coolify lists multiple domains separated by commas.
+        let Some(value) = value.and_then(|value| value.split(',').next()) else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        let url = if value.starts_with("http://") || value.starts_with("https://") {
+            value.to_string()
+        } else {
+            format!("https://{}", value)
+        };
+        if !links.iter().any(|link| link.url == url) {
+            links.push(Link { label, url });
+        }
+    }
+
+    links
+}
+
+/// Labelled resource fields of the payload that are present, for outputs that
+/// show them as a table.
+pub fn payload_fields(payload: &WebhookPayload) -> Vec<(&'static str, String)> {
+    [
+        ("Instance", payload.instance.as_ref()),
+        ("Server", payload.server_name.as_ref()),
+        ("Project", payload.project.as_ref()),
+        ("Application", payload.application_name.as_ref()),
+        ("Database", payload.database_name.as_ref()),
+        ("Container", payload.container_name.as_ref()),
+        ("Task", payload.task_name.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| value.map(|value| (label, value.clone())))
+    .collect()
+}
+
+/// Builds the notification for a webhook. Events polled from a named Coolify
+/// instance are prefixed with its name, e.g. `[staging] Deployment Success`.
 pub fn parse_event(payload: &WebhookPayload) -> Notification {
+    let notification = event_notification(payload);
+    match &payload.instance {
+        Some(instance) => Notification {
+            title: format!("[{}] {}", instance, notification.title),
+            ..notification
+        },
+        None => notification,
+    }
+}
+
+fn event_notification(payload: &WebhookPayload) -> Notification {
     let event = payload.event.as_deref().unwrap_or("unknown");
     match event {
         // docker
@@ -30,15 +189,22 @@ pub fn parse_event(payload: &WebhookPayload) -> Notification {
         // task
         "task_success" => task_success(payload),
         "task_failed" => task_failed(payload),
+        // deployment poller
+        "coolify_api_unreachable" => coolify_api_unreachable(payload),
+        "coolify_api_reachable" => coolify_api_reachable(payload),
         // test
         "test" => test_event(),
-        _ => Notification {
-            title: format!("Event: {}", event),
-            body: payload
-                .message
-                .clone()
-                .unwrap_or_else(|| "No message".to_string()),
-        },
+        _ => unknown_event(payload, event),
+    }
+}
+
+fn unknown_event(payload: &WebhookPayload, event: &str) -> Notification {
+    Notification {
+        title: format!("Event: {}", event),
+        body: payload
+            .message
+            .clone()
+            .unwrap_or_else(|| "No message".to_string()),
     }
 }
 
@@ -172,9 +338,13 @@ fn deployment_success(payload: &WebhookPayload) -> Notification {
     };
     let app = payload.application_name.as_deref().unwrap_or("unknown");
     let project = payload.project.as_deref().unwrap_or("unknown");
+    let mut body = format!("{} was deployed successfully for project: {}", app, project);
+    if let Some(duration_secs) = payload.duration_secs {
+        body.push_str(&format!(" in {}", format_duration(duration_secs)));
+    }
     Notification {
         title: title.to_string(),
-        body: format!("{} was deployed successfully for project: {}", app, project),
+        body,
     }
 }
 
@@ -186,9 +356,13 @@ fn deployment_failed(payload: &WebhookPayload) -> Notification {
     };
     let app = payload.application_name.as_deref().unwrap_or("uknown");
     let project = payload.project.as_deref().unwrap_or("unknown");
+    let mut body = format!("Deployment of {} for project {} failed", app, project);
+    if let Some(duration_secs) = payload.duration_secs {
+        body.push_str(&format!(" after {}", format_duration(duration_secs)));
+    }
     Notification {
         title: title.to_string(),
-        body: format!("Deployment of {} for project {} failed", app, project),
+        body,
     }
 }
 
@@ -220,13 +394,16 @@ fn container_restarted(payload: &WebhookPayload) -> Notification {
     }
 }
 
-fn status_changed(payload: &WebhookPayload) -> Notification {
-    if payload
+fn is_application_stopped(payload: &WebhookPayload) -> bool {
+    payload
         .title
         .as_deref()
         .map(|t| t.to_lowercase() == "application stopped")
         .unwrap_or(false)
-    {
+}
+
+fn status_changed(payload: &WebhookPayload) -> Notification {
+    if is_application_stopped(payload) {
         let app = payload.application_name.as_deref().unwrap_or("unknown");
         return Notification {
             title: "Application Stopped".to_string(),
@@ -234,13 +411,7 @@ fn status_changed(payload: &WebhookPayload) -> Notification {
         };
     }
     // Fallback: same as unknown event (eventParser in JS returns default when handler returns undefined)
-    Notification {
-        title: "Event: status_changed".to_string(),
-        body: payload
-            .message
-            .clone()
-            .unwrap_or_else(|| "No message".to_string()),
-    }
+    unknown_event(payload, "status_changed")
 }
 
 // ---------------------------------------------------------------------------
@@ -275,6 +446,30 @@ fn task_failed(payload: &WebhookPayload) -> Notification {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Deployment poller
+// ---------------------------------------------------------------------------
+
+fn coolify_api_unreachable(payload: &WebhookPayload) -> Notification {
+    let duration = format_duration(payload.duration_secs.unwrap_or(0));
+    let error = payload.message.as_deref().unwrap_or("unknown error");
+    Notification {
+        title: "Coolify API Unreachable".to_string(),
+        body: format!(
+            "Deployments could not be polled for {}: {}",
+            duration, error
+        ),
+    }
+}
+
+fn coolify_api_reachable(payload: &WebhookPayload) -> Notification {
+    let duration = format_duration(payload.duration_secs.unwrap_or(0));
+    Notification {
+        title: "Coolify API Reachable".to_string(),
+        body: format!("Deployments are polled again after {}", duration),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Test
 // ---------------------------------------------------------------------------
@@ -0,0 +1,140 @@
+use std::{collections::BTreeMap, env};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Include/exclude rules evaluated against a raw webhook payload.
+///
+/// An event passes when it matches `include` (if set) and does not match
+/// `exclude` (if set). Patterns may contain `*` wildcards, e.g. `*_failed`
+/// or `deployment_*`.
+///
+/// ```json
+/// {
+///   "include": { "events": ["deployment_*"], "fields": { "project": ["shop"] } },
+///   "exclude": { "fields": { "server_name": ["staging-*"] } }
+/// }
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct EventFilter {
+    #[serde(default)]
+    pub include: Option<FilterRule>,
+    #[serde(default)]
+    pub exclude: Option<FilterRule>,
+}
+
+/// Event name and payload field patterns. `fields` are keyed by field name,
+/// or a dotted path for nested values, and list the accepted patterns.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct FilterRule {
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl EventFilter {
+    /// Reads a filter from a JSON environment variable. A missing variable
+    /// accepts every event.
+    pub fn from_env(name: &str) -> Result<Self, String> {
+        let Ok(json) = env::var(name) else {
+            return Ok(Self::default());
+        };
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", name, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    pub fn matches(&self, payload: &Value) -> bool {
+        let included = self
+            .include
+            .as_ref()
+            .is_none_or(|rule| rule.matches_all(payload));
+        let excluded = self
+            .exclude
+            .as_ref()
+            .is_some_and(|rule| rule.matches_any(payload));
+
+        included && !excluded
+    }
+}
+
+impl FilterRule {
+    /// True when the event name matches one of `events` (if any are listed)
+    /// and every listed field matches one of its patterns.
+    fn matches_all(&self, payload: &Value) -> bool {
+        (self.events.is_empty() || self.event_matches(payload))
+            && self
+                .fields
+                .iter()
+                .all(|(field, patterns)| field_matches(payload, field, patterns))
+    }
+
+    /// True when the event name or any listed field matches.
+    fn matches_any(&self, payload: &Value) -> bool {
+        self.event_matches(payload)
+            || self
+                .fields
+                .iter()
+                .any(|(field, patterns)| field_matches(payload, field, patterns))
+    }
+
+    fn event_matches(&self, payload: &Value) -> bool {
+        let event = payload.get("event").and_then(Value::as_str).unwrap_or("");
+        self.events.iter().any(|pattern| glob_match(pattern, event))
+    }
+}
+
+fn field_matches(payload: &Value, field: &str, patterns: &[String]) -> bool {
+    let Some(value) = field_value(payload, field) else {
+        return false;
+    };
+
+    patterns.iter().any(|pattern| glob_match(pattern, &value))
+}
+
+fn field_value(payload: &Value, field: &str) -> Option<String> {
+    let value = field
+        .split('.')
+        .try_fold(payload, |value, key| value.get(key))?;
+
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Case-insensitive match where `*` stands for any sequence of characters.
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let value = value.to_lowercase();
+    let mut parts = pattern.split('*');
+
+    let Some(first) = parts.next() else {
+        return value.is_empty();
+    };
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
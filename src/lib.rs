@@ -1,14 +1,23 @@
 use serde::{Deserialize, Serialize};
 
 pub mod event_parser;
+pub mod filter;
+pub mod logging;
+pub mod metrics;
+pub mod routing;
+pub mod signing;
+pub mod template;
 pub mod utils;
 pub mod services;
 pub mod state;
 
 pub use services::expo::ExpoService;
 pub use services::deployment_poller::DeploymentPollerService;
+pub use services::history::HistoryStore;
 pub use services::repeater::WebhookRepeaterService;
+pub use services::sink::NotificationSink;
 pub use services::updater::UpdaterService;
+pub use signing::verify_signature;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct WebhookPayload {
@@ -27,6 +36,16 @@ pub struct WebhookPayload {
     pub container_name: Option<String>,
     pub affected_servers_count: Option<u64>,
     pub task_name: Option<String>,
+    pub url: Option<String>,
+    pub deployment_url: Option<String>,
+    pub fqdn: Option<String>,
+    /// Name of the relay endpoint that received the webhook, see
+    /// [`services::endpoints::WebhookEndpoint`].
+    pub endpoint: Option<String>,
+    /// How long a deployment took, added by the deployment poller.
+    pub duration_secs: Option<u64>,
+    /// Name of the Coolify instance a deployment poller event came from.
+    pub instance: Option<String>,
 }
 
 impl WebhookPayload {
@@ -0,0 +1,33 @@
+use std::env;
+
+use tracing_subscriber::EnvFilter;
+
+/// Installs the global log subscriber.
+///
+/// `LOG_LEVEL` accepts a level (`info`, `debug`, ...) or a full filter
+/// directive such as `info,coolify_expo_notification_relay=debug`.
+/// `LOG_FORMAT=json` switches from human readable lines to one JSON object
+/// per line.
+pub fn init() {
+    let level = env::var("LOG_LEVEL")
+        .ok()
+        .map(|level| level.trim().to_string())
+        .filter(|level| !level.is_empty())
+        .unwrap_or("info".to_string());
+    let filter = EnvFilter::try_new(&level).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let json = env::var("LOG_FORMAT")
+        .map(|format| format.trim().eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if json {
+        builder.json().flatten_event(true).try_init()
+    } else {
+        builder.try_init()
+    };
+
+    if let Err(error) = result {
+        eprintln!("Failed to initialize logging: {}", error);
+    }
+}
@@ -1,28 +1,40 @@
 use axum::{
-    Router,
-    routing::{get, post},
+    Router, middleware,
+    routing::{get, post, put},
 };
 use coolify_expo_notification_relay::{
-    DeploymentPollerService, ExpoService, UpdaterService, WebhookRepeaterService, services,
-    state::AppState, utils::parse_expo_push_tokens,
+    DeploymentPollerService, ExpoService, HistoryStore, UpdaterService, WebhookRepeaterService,
+    logging,
+    routing::{self, Routes},
+    services::{
+        self,
+        admin::{self, AdminAuth},
+        dashboard,
+        deployment_poller::PollerStatus,
+        endpoints::WebhookEndpoint,
+        groups::DeviceGroups,
+        health,
+        sink::{self, NotificationSink},
+    },
+    state::AppState,
+    utils::parse_expo_push_tokens,
 };
-use reqwest::StatusCode;
 use std::env;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
+    logging::init();
+
     let Ok(expo_push_tokens) = parse_expo_push_tokens() else {
-        eprintln!("Environment variable EXPO_PUSH_TOKENS is not set.");
-        eprintln!("Please set the environment variable and try again.");
-        eprintln!("-------------------------------------------------");
-        eprintln!("Example: EXPO_PUSH_TOKENS='ExponentPushToken[1234567890]'");
-        eprintln!(
+        error!("Environment variable EXPO_PUSH_TOKENS is not set.");
+        error!("Please set the environment variable and try again.");
+        error!("Example: EXPO_PUSH_TOKENS='ExponentPushToken[1234567890]'");
+        error!(
             "For multiple tokens, use a comma-separated list: EXPO_PUSH_TOKENS='ExponentPushToken[1234567890],ExponentPushToken[1234567891]'"
         );
-        eprintln!();
-        eprintln!("You can find your Expo push tokens in the app settings.");
-        eprintln!("-------------------------------------------------");
+        error!("You can find your Expo push tokens in the app settings.");
         std::process::exit(1)
     };
 
@@ -33,68 +45,207 @@ async fn main() {
 
     let http_client = reqwest::Client::new();
 
+    let history = match HistoryStore::from_env() {
+        Ok(history) => Arc::new(history),
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    HistoryStore::start_pruning(history.clone());
+
+    let mut expo = ExpoService::new(expo_push_tokens, expo_push_url, http_client.clone());
+    expo.groups = match DeviceGroups::from_env() {
+        Ok(groups) => groups,
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    match history.device_groups() {
+        Ok(groups) => expo.groups.load(groups),
+        Err(error) => error!("{}", error),
+    }
+    let expo = Arc::new(expo);
+    let repeater = match WebhookRepeaterService::from_env(http_client.clone()) {
+        Ok(repeater) => Arc::new(repeater),
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let endpoints = match WebhookEndpoint::from_env(&http_client, &repeater) {
+        Ok(endpoints) => endpoints,
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut sinks: Vec<Arc<dyn NotificationSink>> = vec![expo.clone(), repeater.clone()];
+    match sink::chat_sinks_from_env(&http_client) {
+        Ok(chat_sinks) => sinks.extend(chat_sinks),
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
+    let routes = match Routes::from_env() {
+        Ok(routes) => routes,
+        Err(error) => {
+            error!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    for destination in routes.destinations() {
+        let output = destination.split(':').next().unwrap_or_default();
+        if output != routing::ALL_OUTPUTS && !sinks.iter().any(|sink| sink.name() == output) {
+            warn!(
+                destination = %destination,
+                "Routing destination does not match a configured output"
+            );
+        }
+    }
+    let routed_groups = routes
+        .destinations()
+        .filter_map(|destination| destination.strip_prefix("expo:"))
+        .map(String::from)
+        .collect::<Vec<String>>();
+    if let Err(error) = expo.groups.check(&routed_groups) {
+        error!("Invalid ROUTING_RULES: {}", error);
+        std::process::exit(1);
+    }
+    for endpoint in endpoints.iter() {
+        if let Err(error) = expo.groups.check(&endpoint.devices) {
+            error!(
+                "Invalid WEBHOOK_ENDPOINTS: endpoint {}: {}",
+                endpoint.name, error
+            );
+            std::process::exit(1);
+        }
+    }
+
     let state = Arc::new(AppState {
-        expo: ExpoService::new(expo_push_tokens, expo_push_url, http_client.clone()),
-        repeater: WebhookRepeaterService {
-            urls: env::var("WEBHOOK_RELAY_URLS")
-                .unwrap_or("".to_string())
-                .split(',')
-                .map(|url| url.trim().to_string())
-                .filter(|url| !url.is_empty())
-                .collect::<Vec<String>>(),
-            client: http_client.clone(),
-        },
+        sinks,
+        routes,
+        expo,
+        repeater,
+        history,
+        endpoints,
+        poller_status: RwLock::new(PollerStatus::default()),
+        admin: AdminAuth::from_env(),
         http_client: http_client.clone(),
     });
 
     let state_clone = state.clone();
     tokio::spawn(async move {
         let mut updater = UpdaterService::with_client(state_clone.http_client.clone());
+        updater.notify_groups = env::var("UPDATE_NOTIFICATION_GROUPS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|group| group.trim().to_string())
+            .filter(|group| !group.is_empty())
+            .collect();
         let update_result = updater.check_for_updates().await;
 
         match update_result {
             Ok(Some(release)) => {
-                println!("-------------------------------------------------");
-                println!("Latest version: {}", release.tag_name);
-                println!("Current version: {}", updater.get_current_version());
-                println!(
+                warn!(
+                    latest_version = %release.tag_name,
+                    current_version = %updater.get_current_version(),
+                    "A new version is available"
+                );
+                info!(
                     "If running in docker, you can update by running: docker pull ghcr.io/jacxk/coolify-expo-notification-relay:latest"
                 );
-                println!("If running on coolify, you can redeploy the application.");
-                println!("-------------------------------------------------");
+                info!("If running on coolify, you can redeploy the application.");
 
                 if let Err(error) = updater.send_notification_to_device(&state_clone.expo).await {
-                    eprintln!("Failed to send notification to device: {}", error);
+                    error!("Failed to send notification to device: {}", error);
                 }
             }
-            Err(error) => eprintln!("Failed to check for updates: {}", error),
+            Err(error) => error!("Failed to check for updates: {}", error),
             _ => (),
         }
     });
 
     if let Err(error) = DeploymentPollerService::start_polling(state.clone()) {
-        eprintln!("{}", error);
+        error!("{}", error);
         std::process::exit(1);
     }
 
-    let app = Router::new()
-        .route("/health", get(|| async { (StatusCode::OK, "OK") }))
+    let mut app = Router::new()
+        .route("/health", get(health::show_health))
+        .route("/ready", get(health::show_ready))
+        .route("/metrics", get(services::show_metrics))
         .route(webhook_path.as_str(), post(services::handle_webhook))
-        .with_state(state);
+        .route("/hooks/{name}", post(services::handle_endpoint_webhook));
+
+    let admin_enabled = state.admin.is_some();
+    if admin_enabled {
+        app = app.merge(
+            Router::new()
+                .route("/dashboard", get(dashboard::show_dashboard))
+                .route("/events", get(services::list_events))
+                .route("/dashboard/test", post(dashboard::send_test_notification))
+                .route(
+                    "/dashboard/events/{id}/resend",
+                    post(dashboard::resend_event),
+                )
+                .route("/admin/dead-letters", get(admin::list_dead_letters))
+                .route("/admin/routes/dry-run", post(admin::dry_run_route))
+                .route("/admin/groups", get(admin::list_device_groups))
+                .route(
+                    "/admin/groups/{name}",
+                    put(admin::update_device_group).delete(admin::delete_device_group),
+                )
+                .route(
+                    "/admin/dead-letters/{id}/replay",
+                    post(admin::replay_dead_letter),
+                )
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    admin::require_admin,
+                )),
+        );
+    }
+
+    let outputs = state
+        .sinks
+        .iter()
+        .map(|sink| sink.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let endpoint_names = state
+        .endpoints
+        .iter()
+        .map(|endpoint| endpoint.name.clone())
+        .collect::<Vec<_>>();
+    let app = app.with_state(state);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
         .await
         .unwrap_or_else(|e| {
-            eprintln!("Failed to bind to port {}: {}", port, e);
+            error!("Failed to bind to port {}: {}", port, e);
             std::process::exit(1);
         });
 
-    println!("Relay server is running");
-    println!("Health check: http://localhost:{}/health", port);
-    println!("Webhook: http://localhost:{}{}", port, webhook_path);
+    info!("Relay server is running");
+    info!("Outputs: {}", outputs);
+    info!("Health check: http://localhost:{}/health", port);
+    info!("Readiness check: http://localhost:{}/ready", port);
+    info!("Webhook: http://localhost:{}{}", port, webhook_path);
+    for name in endpoint_names {
+        info!("Webhook endpoint: http://localhost:{}/hooks/{}", port, name);
+    }
+    info!("Metrics: http://localhost:{}/metrics", port);
+    if admin_enabled {
+        info!("Dashboard: http://localhost:{}/dashboard", port);
+        info!("Event history: http://localhost:{}/events", port);
+    }
 
     axum::serve(listener, app).await.unwrap_or_else(|e| {
-        eprintln!("Failed to serve application: {}", e);
+        error!("Failed to serve application: {}", e);
         std::process::exit(1);
     });
 }
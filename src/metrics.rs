@@ -0,0 +1,138 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::event_parser;
+
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+pub static WEBHOOKS_RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "relay_webhooks_received_total",
+            "Webhooks received, by event type.",
+        ),
+        &["event"],
+    ))
+});
+
+pub static PARSE_FALLBACKS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "relay_parse_fallbacks_total",
+            "Events rendered by the unknown-event fallback, by event type.",
+        ),
+        &["event"],
+    ))
+});
+
+pub static EXPO_SENDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("relay_expo_sends_total", "Expo push sends, by outcome."),
+        &["outcome"],
+    ))
+});
+
+pub static EXPO_SEND_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "relay_expo_send_duration_seconds",
+            "Latency of Expo push requests, by outcome.",
+        ),
+        &["outcome"],
+    ))
+});
+
+pub static REPEATER_FORWARDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "relay_repeater_forwards_total",
+            "Webhooks forwarded to repeater URLs, by target and outcome.",
+        ),
+        &["target", "outcome"],
+    ))
+});
+
+pub static REPEATER_FORWARD_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "relay_repeater_forward_duration_seconds",
+            "Time spent forwarding to a repeater URL, including retries, by target and outcome.",
+        ),
+        &["target", "outcome"],
+    ))
+});
+
+pub static OUTPUT_SENDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "relay_output_sends_total",
+            "Messages sent to chat outputs, by output and outcome.",
+        ),
+        &["output", "outcome"],
+    ))
+});
+
+pub static POLLER_CYCLES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(IntCounter::new(
+        "relay_poller_cycles_total",
+        "Deployment poller cycles.",
+    ))
+});
+
+pub static POLLER_ERRORS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(IntCounter::new(
+        "relay_poller_errors_total",
+        "Deployment poller cycles that failed to read the Coolify API.",
+    ))
+});
+
+pub static OUTBOX_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register(IntGauge::new(
+        "relay_outbox_depth",
+        "Accepted webhooks whose delivery has not finished yet.",
+    ))
+});
+
+/// Event names come from the request body, so anything the parser does not
+/// know is collapsed into one label to keep the series count bounded.
+pub fn event_label(event: Option<&str>) -> &str {
+    match event {
+        Some(event) if event_parser::is_known_event(event) => event,
+        _ => "unknown",
+    }
+}
+
+/// Renders every registered metric in the Prometheus text format.
+pub fn render() -> Result<String, String> {
+    // Metrics are registered on first use; force them so every series is
+    // exported from the first scrape on.
+    LazyLock::force(&WEBHOOKS_RECEIVED);
+    LazyLock::force(&PARSE_FALLBACKS);
+    LazyLock::force(&EXPO_SENDS);
+    LazyLock::force(&EXPO_SEND_DURATION);
+    LazyLock::force(&REPEATER_FORWARDS);
+    LazyLock::force(&REPEATER_FORWARD_DURATION);
+    LazyLock::force(&OUTPUT_SENDS);
+    LazyLock::force(&POLLER_CYCLES);
+    LazyLock::force(&POLLER_ERRORS);
+    LazyLock::force(&OUTBOX_DEPTH);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .map_err(|e| format!("Failed to encode metrics: {}", e))?;
+
+    String::from_utf8(buffer).map_err(|e| format!("Failed to encode metrics: {}", e))
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(metric: prometheus::Result<T>) -> T {
+    let metric = metric.expect("metric definition should be valid");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("metric should only be registered once");
+    metric
+}
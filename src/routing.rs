@@ -0,0 +1,228 @@
+use std::env;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventSeverity},
+    filter::glob_match,
+};
+
+/// Destination that stands for every configured output.
+pub const ALL_OUTPUTS: &str = "*";
+
+/// Ordered rules that decide which outputs receive an event.
+///
+/// Rules are evaluated top to bottom and every matching rule adds its
+/// destinations, until a rule with `stop` matches. Events that match no rule
+/// take the `default` route, which sends to every output unless configured.
+///
+/// Destinations name an output, e.g. `slack`, or targets of an output, e.g.
+/// `repeater:audit` for the repeater target named `audit` or `expo:oncall`
+/// for the `oncall` device group.
+///
+/// ```json
+/// {
+///   "rules": [
+///     { "name": "failures", "match": { "severity": ["critical"] }, "destinations": ["slack", "expo"], "stop": true },
+///     { "match": { "events": ["deployment_*"], "project": ["shop"] }, "destinations": ["repeater:shop"] }
+///   ],
+///   "default": ["expo"]
+/// }
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct Routes {
+    #[serde(default)]
+    pub rules: Vec<RouteRule>,
+    #[serde(default = "Routes::default_route")]
+    pub default: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RouteRule {
+    /// Shown in logs and dry runs. Unnamed rules are shown by position.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename = "match")]
+    pub conditions: RouteMatch,
+    pub destinations: Vec<String>,
+    /// Skips the remaining rules when this one matches.
+    #[serde(default)]
+    pub stop: bool,
+}
+
+/// Conditions of a rule. Every condition that is set must match; lists
+/// accept any of their patterns, which may contain `*` wildcards.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct RouteMatch {
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub server_name: Vec<String>,
+    #[serde(default)]
+    pub project: Vec<String>,
+    #[serde(default)]
+    pub application_name: Vec<String>,
+    /// Names of the [webhook endpoints](crate::services::endpoints) that
+    /// received the event.
+    #[serde(default)]
+    pub endpoint: Vec<String>,
+    /// Matched against the payload `message`, or the notification body when
+    /// the payload has none.
+    #[serde(default, deserialize_with = "regex")]
+    pub message: Option<Regex>,
+    #[serde(default)]
+    pub severity: Vec<EventSeverity>,
+}
+
+/// The route an event takes.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    /// Names of the rules that matched, in evaluation order.
+    pub rules: Vec<String>,
+    /// True when no rule matched and the default route was used.
+    pub default: bool,
+    pub destinations: Vec<String>,
+}
+
+impl Default for Routes {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: Self::default_route(),
+        }
+    }
+}
+
+impl Routes {
+    fn default_route() -> Vec<String> {
+        vec![ALL_OUTPUTS.to_string()]
+    }
+
+    /// Reads the rules from the `ROUTING_RULES` JSON environment variable.
+    /// Without rules, every event is sent to every output.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(json) = env::var("ROUTING_RULES") else {
+            return Ok(Self::default());
+        };
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(&json).map_err(|e| format!("Invalid ROUTING_RULES: {}", e))
+    }
+
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let routes: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for (index, rule) in routes.rules.iter().enumerate() {
+            if rule.destinations.is_empty() {
+                return Err(format!("Rule {} has no destinations.", rule.label(index)));
+            }
+        }
+        Ok(routes)
+    }
+
+    pub fn route(&self, payload: &Value, notification: &Notification) -> Route {
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+
+        let mut route = Route {
+            rules: Vec::new(),
+            default: false,
+            destinations: Vec::new(),
+        };
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.conditions.matches(&webhook_payload, notification) {
+                continue;
+            }
+
+            route.rules.push(rule.label(index));
+            for destination in rule.destinations.iter() {
+                if !route.destinations.contains(destination) {
+                    route.destinations.push(destination.clone());
+                }
+            }
+            if rule.stop {
+                break;
+            }
+        }
+
+        if route.rules.is_empty() {
+            route.default = true;
+            route.destinations = self.default.clone();
+        }
+        route
+    }
+
+    /// Every destination used by a rule or the default route.
+    pub fn destinations(&self) -> impl Iterator<Item = &String> {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.destinations.iter())
+            .chain(self.default.iter())
+    }
+}
+
+impl RouteRule {
+    fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+}
+
+impl RouteMatch {
+    pub fn matches(&self, payload: &WebhookPayload, notification: &Notification) -> bool {
+        let event = payload.event.as_deref().unwrap_or("");
+
+        any_match(&self.events, Some(event))
+            && any_match(&self.server_name, payload.server_name.as_deref())
+            && any_match(&self.project, payload.project.as_deref())
+            && any_match(&self.application_name, payload.application_name.as_deref())
+            && any_match(&self.endpoint, payload.endpoint.as_deref())
+            && self.message.as_ref().is_none_or(|regex| {
+                regex.is_match(payload.message.as_deref().unwrap_or(&notification.body))
+            })
+            && (self.severity.is_empty()
+                || self.severity.contains(&event_parser::event_severity(event)))
+    }
+}
+
+impl Route {
+    /// The targets of `output` this route selects: `None` when the output is
+    /// not part of the route, an empty list when all of its targets are.
+    pub fn targets(&self, output: &str) -> Option<Vec<String>> {
+        if self
+            .destinations
+            .iter()
+            .any(|destination| destination == ALL_OUTPUTS || destination == output)
+        {
+            return Some(Vec::new());
+        }
+
+        let targets = self
+            .destinations
+            .iter()
+            .filter_map(|destination| destination.split_once(':'))
+            .filter(|(name, _)| *name == output)
+            .map(|(_, target)| target.to_string())
+            .collect::<Vec<String>>();
+        (!targets.is_empty()).then_some(targets)
+    }
+}
+
+/// Patterns with no entries accept every value.
+fn any_match(patterns: &[String], value: Option<&str>) -> bool {
+    if patterns.is_empty() {
+        return true;
+    }
+
+    value.is_some_and(|value| patterns.iter().any(|pattern| glob_match(pattern, value)))
+}
+
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|pattern| Regex::new(&pattern).map_err(serde::de::Error::custom))
+        .transpose()
+}
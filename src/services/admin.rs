@@ -0,0 +1,348 @@
+use std::{env, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+
+use crate::{
+    WebhookPayload,
+    event_parser::{self, EventSeverity},
+    routing::Route,
+    services::{groups::DeviceGroups, history::DeadLetterQuery},
+    state::AppState,
+    utils::constant_time_eq,
+};
+
+pub struct AdminAuth {
+    token: String,
+}
+
+impl AdminAuth {
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        env::var("ADMIN_TOKEN")
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+            .map(Self::new)
+    }
+
+    /// Accepts either `Authorization: Bearer <token>` or HTTP basic auth with
+    /// any username and the token as password, so browsers can log in too.
+    pub fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(authorization) = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return false;
+        };
+
+        if let Some(token) = authorization.strip_prefix("Bearer ") {
+            return constant_time_eq(token.trim().as_bytes(), self.token.as_bytes());
+        }
+
+        let Some(encoded) = authorization.strip_prefix("Basic ") else {
+            return false;
+        };
+        let Ok(decoded) = STANDARD.decode(encoded.trim()) else {
+            return false;
+        };
+        let Ok(credentials) = String::from_utf8(decoded) else {
+            return false;
+        };
+
+        credentials
+            .split_once(':')
+            .map(|(_, password)| constant_time_eq(password.as_bytes(), self.token.as_bytes()))
+            .unwrap_or(false)
+    }
+}
+
+pub async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = state
+        .admin
+        .as_ref()
+        .map(|admin| admin.is_authorized(request.headers()))
+        .unwrap_or(false);
+
+    if !authorized {
+        let mut response = (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"relay admin\""),
+        );
+        return response;
+    }
+
+    if !is_same_origin(&request) {
+        return (StatusCode::FORBIDDEN, "Cross-origin request rejected").into_response();
+    }
+
+    next.run(request).await
+}
+
+pub async fn list_dead_letters(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DeadLetterQuery>,
+) -> Response {
+    match state.history.dead_letters(&query) {
+        Ok(dead_letters) => (StatusCode::OK, Json(dead_letters)).into_response(),
+        Err(error) => {
+            error!("{}", error);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to query dead letters",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Sends a dead letter to its target again. Responds with the forward outcome,
+/// using `502 Bad Gateway` when the target still rejects it.
+pub async fn replay_dead_letter(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Response {
+    let dead_letter = match state.history.get_dead_letter(id) {
+        Ok(Some(dead_letter)) => dead_letter,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Dead letter not found").into_response(),
+        Err(error) => {
+            error!("{}", error);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read dead letter",
+            )
+                .into_response();
+        }
+    };
+
+    let notification = match WebhookPayload::from_value(dead_letter.payload.clone()) {
+        Ok(payload) => event_parser::parse_event(&payload),
+        Err(_) => return (StatusCode::UNPROCESSABLE_ENTITY, "Invalid payload").into_response(),
+    };
+
+    // Targets of a webhook endpoint are replayed with the endpoint's settings.
+    let repeater = state
+        .endpoints
+        .iter()
+        .filter_map(|endpoint| endpoint.repeater.as_ref())
+        .find(|repeater| {
+            repeater
+                .targets
+                .iter()
+                .any(|target| target.url == dead_letter.target_url)
+        })
+        .unwrap_or(&state.repeater);
+    let outcome = match repeater
+        .forward_to(&dead_letter.target_url, &dead_letter.payload, &notification)
+        .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    if let Err(error) = state.history.record_replay(id, &outcome) {
+        error!("{}", error);
+    }
+
+    let status = if outcome.success {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    (status, Json(outcome)).into_response()
+}
+
+/// The route an event would take, as shown by [`dry_run_route`].
+#[derive(Serialize)]
+pub struct RouteDryRun {
+    pub event: Option<String>,
+    pub severity: EventSeverity,
+    #[serde(flatten)]
+    pub route: Route,
+    /// The configured outputs the event would be delivered to.
+    pub outputs: Vec<RoutedOutput>,
+}
+
+#[derive(Serialize)]
+pub struct RoutedOutput {
+    pub name: &'static str,
+    /// The selected targets, or empty when all of them are.
+    pub targets: Vec<String>,
+}
+
+/// Evaluates the routing rules for a webhook payload without delivering it.
+pub async fn dry_run_route(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<Value>,
+) -> Response {
+    let Ok(webhook_payload) = WebhookPayload::from_value(payload.clone()) else {
+        return (StatusCode::UNPROCESSABLE_ENTITY, "Invalid payload").into_response();
+    };
+    let notification = event_parser::parse_event(&webhook_payload);
+    let route = state.routes.route(&payload, &notification);
+
+    let outputs = state
+        .sinks
+        .iter()
+        .filter_map(|sink| {
+            Some(RoutedOutput {
+                name: sink.name(),
+                targets: route.targets(sink.name())?,
+            })
+        })
+        .collect();
+
+    let dry_run = RouteDryRun {
+        severity: event_parser::event_severity(webhook_payload.event.as_deref().unwrap_or("")),
+        event: webhook_payload.event,
+        route,
+        outputs,
+    };
+    (StatusCode::OK, Json(dry_run)).into_response()
+}
+
+pub async fn list_device_groups(State(state): State<Arc<AppState>>) -> Response {
+    (StatusCode::OK, Json(state.expo.groups.list())).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct DeviceGroupUpdate {
+    pub members: Vec<String>,
+}
+
+/// Replaces the members of a group that are managed through the API. Members
+/// from the configuration stay in the group.
+pub async fn update_device_group(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(update): Json<DeviceGroupUpdate>,
+) -> Response {
+    if let Err(e) = DeviceGroups::validate(&name, &update.members) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+    let configured = state
+        .expo
+        .groups
+        .get(&name)
+        .is_some_and(|group| !group.configured.is_empty());
+    if update.members.is_empty()
+        && !configured
+        && let Some(conflict) = referenced_group_conflict(&state, &name)
+    {
+        return conflict;
+    }
+    if let Err(error) = state.history.set_device_group(&name, &update.members) {
+        error!("{}", error);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update device group",
+        )
+            .into_response();
+    }
+    state.expo.groups.set(&name, update.members);
+
+    match state.expo.groups.get(&name) {
+        Some(group) => (StatusCode::OK, Json(group)).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+pub async fn delete_device_group(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(group) = state.expo.groups.get(&name) else {
+        return (StatusCode::NOT_FOUND, "Device group not found").into_response();
+    };
+    if !group.configured.is_empty() {
+        return (
+            StatusCode::CONFLICT,
+            "Members from EXPO_DEVICE_GROUPS can only be removed in the configuration",
+        )
+            .into_response();
+    }
+    if let Some(conflict) = referenced_group_conflict(&state, &name) {
+        return conflict;
+    }
+    if let Err(error) = state.history.set_device_group(&name, &[]) {
+        error!("{}", error);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to delete device group",
+        )
+            .into_response();
+    }
+    state.expo.groups.set(&name, Vec::new());
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// A group that routing rules or endpoints send to must keep existing, since
+/// the relay refuses to start when they name an unknown group.
+fn referenced_group_conflict(state: &AppState, name: &str) -> Option<Response> {
+    let mut references = Vec::new();
+    if state
+        .routes
+        .destinations()
+        .any(|destination| destination.strip_prefix("expo:") == Some(name))
+    {
+        references.push("ROUTING_RULES".to_string());
+    }
+    references.extend(
+        state
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.devices.iter().any(|device| device == name))
+            .map(|endpoint| format!("endpoint {}", endpoint.name)),
+    );
+    if references.is_empty() {
+        return None;
+    }
+
+    Some(
+        (
+            StatusCode::CONFLICT,
+            format!("Device group is still used by {}", references.join(", ")),
+        )
+            .into_response(),
+    )
+}
+
+/// Browsers replay basic auth credentials on cross-site form posts, so state
+/// changing requests must come from the relay's own pages.
+fn is_same_origin(request: &Request) -> bool {
+    if request.method() == axum::http::Method::GET {
+        return true;
+    }
+
+    let headers = request.headers();
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let Some(host) = headers.get(header::HOST).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    origin
+        .split_once("://")
+        .map(|(_, origin_host)| origin_host == host)
+        .unwrap_or(false)
+}
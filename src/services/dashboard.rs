@@ -0,0 +1,264 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use tracing::error;
+
+use crate::{
+    services::{
+        self,
+        expo::ExpoDelivery,
+        history::{EventQuery, EventRecord},
+    },
+    state::AppState,
+};
+
+const RECENT_EVENTS: u32 = 25;
+
+pub async fn show_dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
+    let events = state
+        .history
+        .query(&EventQuery {
+            limit: Some(RECENT_EVENTS),
+            ..Default::default()
+        })
+        .map(|page| page.events)
+        .inspect_err(|error| error!("{}", error))
+        .unwrap_or_default();
+
+    let sections = [
+        section("Recent events", &events_table(&events)),
+        section(
+            "Push tokens",
+            &tokens_table(&state.expo.expo_push_tokens, &events),
+        ),
+        section("Dead tokens", &dead_tokens_list(&state.expo.dead_tokens())),
+        section("Repeaters", &repeaters_table(&state)),
+        section("Deployment poller", &poller_table(&state)),
+    ];
+
+    Html(format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Relay dashboard</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 1rem; }}
+th, td {{ text-align: left; padding: .35rem .6rem; border-bottom: 1px solid #ddd; vertical-align: top; }}
+.delivered, .ok {{ color: #1a7f37; }}
+.failed, .error {{ color: #cf222e; }}
+.partial, .pending {{ color: #9a6700; }}
+.muted {{ color: #777; }}
+form {{ display: inline; }}
+</style>
+</head>
+<body>
+<h1>Coolify Expo Notification Relay</h1>
+<form method="post" action="/dashboard/test"><button type="submit">Send test notification</button></form>
+{}
+<script>
+for (const el of document.querySelectorAll("time[data-ts]")) {{
+  el.textContent = new Date(Number(el.dataset.ts) * 1000).toLocaleString();
+}}
+</script>
+</body>
+</html>"#,
+        sections.join("\n")
+    ))
+}
+
+pub async fn send_test_notification(State(state): State<Arc<AppState>>) -> Redirect {
+    services::handle_webhook(State(state), Json(json!({ "event": "test" }))).await;
+    Redirect::to("/dashboard")
+}
+
+pub async fn resend_event(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> Response {
+    match state.history.get(id) {
+        Ok(Some(event)) => {
+            let endpoint = event
+                .payload
+                .get("endpoint")
+                .and_then(|name| name.as_str())
+                .and_then(|name| state.endpoint(name));
+            services::accept_webhook(state.clone(), endpoint, event.payload);
+            Redirect::to("/dashboard").into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, "Event not found").into_response(),
+        Err(error) => {
+            error!("{}", error);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read event").into_response()
+        }
+    }
+}
+
+fn section(title: &str, content: &str) -> String {
+    format!("<h2>{}</h2>\n{}", escape(title), content)
+}
+
+fn events_table(events: &[EventRecord]) -> String {
+    if events.is_empty() {
+        return "<p class=\"muted\">No events received yet.</p>".to_string();
+    }
+
+    let rows = events
+        .iter()
+        .map(|event| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}<br><span class=\"muted\">{}</span></td>\
+                 <td>{}</td><td class=\"{status}\">{status}</td>\
+                 <td><form method=\"post\" action=\"/dashboard/events/{}/resend\">\
+                 <button type=\"submit\">Re-send</button></form></td></tr>",
+                timestamp(Some(event.received_at as u64)),
+                escape(&event.event),
+                escape(&event.notification.title),
+                escape(&event.notification.body),
+                escape(
+                    event
+                        .application_name
+                        .as_deref()
+                        .or(event.server_name.as_deref())
+                        .unwrap_or("")
+                ),
+                event.id,
+                status = event.delivery_status.as_str(),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<table><tr><th>Received</th><th>Event</th><th>Notification</th><th>Resource</th>\
+         <th>Delivery</th><th></th></tr>{}</table>",
+        rows
+    )
+}
+
+fn tokens_table(tokens: &[String], events: &[EventRecord]) -> String {
+    let mut latest: HashMap<&str, (&EventRecord, &ExpoDelivery)> = HashMap::new();
+    for event in events {
+        for delivery in event.deliveries.iter() {
+            latest
+                .entry(delivery.token.as_str())
+                .or_insert((event, delivery));
+        }
+    }
+
+    let rows = tokens
+        .iter()
+        .map(|token| match latest.get(token.as_str()) {
+            Some((event, delivery)) => {
+                let status = if delivery.success { "ok" } else { "error" };
+                format!(
+                    "<tr><td>{}</td><td class=\"{status}\">{status}</td><td>{}</td><td class=\"muted\">{}</td></tr>",
+                    escape(token),
+                    timestamp(Some(event.received_at as u64)),
+                    escape(delivery.detail.as_deref().unwrap_or("")),
+                )
+            }
+            None => format!(
+                "<tr><td>{}</td><td class=\"muted\">no recent pushes</td><td></td><td></td></tr>",
+                escape(token)
+            ),
+        })
+        .collect::<String>();
+
+    format!(
+        "<table><tr><th>Token</th><th>Last push</th><th>At</th><th>Detail</th></tr>{}</table>",
+        rows
+    )
+}
+
+fn dead_tokens_list(tokens: &[String]) -> String {
+    if tokens.is_empty() {
+        return "<p class=\"muted\">No dead tokens.</p>".to_string();
+    }
+
+    let items = tokens
+        .iter()
+        .map(|token| format!("<li class=\"error\">{}</li>", escape(token)))
+        .collect::<String>();
+    format!("<ul>{}</ul>", items)
+}
+
+fn repeaters_table(state: &AppState) -> String {
+    let statuses = state.repeater.statuses();
+    if statuses.is_empty() {
+        return "<p class=\"muted\">No repeater URLs configured.</p>".to_string();
+    }
+
+    let rows = statuses
+        .iter()
+        .map(|status| {
+            let health = match (&status.last_error, status.last_attempt_at) {
+                (_, None) => "<td class=\"muted\">idle</td>".to_string(),
+                (Some(error), _) => format!("<td class=\"error\">{}</td>", escape(error)),
+                (None, _) => "<td class=\"ok\">ok</td>".to_string(),
+            };
+            format!(
+                "<tr><td>{}</td>{}<td>{}</td><td>{}</td></tr>",
+                escape(&status.url),
+                health,
+                timestamp(status.last_attempt_at),
+                timestamp(status.last_success_at),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<table><tr><th>URL</th><th>Status</th><th>Last attempt</th><th>Last success</th></tr>{}</table>",
+        rows
+    )
+}
+
+fn poller_table(state: &AppState) -> String {
+    let Ok(status) = state.poller_status.read().map(|status| status.clone()) else {
+        return "<p class=\"error\">Poller status is unavailable.</p>".to_string();
+    };
+
+    if !status.enabled {
+        return "<p class=\"muted\">Deployment polling is disabled.</p>".to_string();
+    }
+
+    let rows = status
+        .instances
+        .iter()
+        .map(|instance| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"error\">{}</td><td>{}</td></tr>",
+                escape(instance.name.as_deref().unwrap_or("default")),
+                timestamp(instance.last_poll_at),
+                timestamp(instance.last_success_at),
+                escape(instance.last_error.as_deref().unwrap_or("")),
+                escape(&instance.in_progress.join(", ")),
+            )
+        })
+        .collect::<String>();
+    format!(
+        "<table><tr><th>Instance</th><th>Last poll</th><th>Last success</th>\
+         <th>Last error</th><th>In progress</th></tr>{}</table>",
+        rows
+    )
+}
+
+fn timestamp(value: Option<u64>) -> String {
+    match value {
+        Some(value) => format!("<time data-ts=\"{0}\">{0}</time>", value),
+        None => "<span class=\"muted\">never</span>".to_string(),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -1,18 +1,143 @@
-use std::{env, sync::Arc, time::Duration};
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
 
-use axum::Json;
-use axum::extract::State;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::json;
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
+use crate::metrics;
+use crate::services::history::{HistoryStore, PolledDeployment};
 use crate::state::AppState;
+use crate::utils::unix_timestamp;
 use crate::{services};
 
 pub struct DeploymentPollerService {
+    /// Name of the Coolify instance, added to the events of this poller as
+    /// `instance`. `None` for the instance set with `COOLIFY_API_URL`.
+    pub instance: Option<String>,
     pub api_url: String,
     pub api_token: String,
     pub poll_interval_secs: u64,
+    /// Interval used instead of `poll_interval_secs` while deployments are
+    /// in progress, if it is shorter.
+    pub active_poll_interval_secs: u64,
+    /// Ceiling of the exponential backoff after failed polls.
+    pub max_backoff_secs: u64,
+    /// How long the API may fail before an outage is notified. `0` disables
+    /// the notification.
+    pub outage_notify_secs: u64,
     pub api_endpoint: String,
     client: reqwest::Client,
-    deployments: Vec<String>,
+    deployments: Vec<TrackedDeployment>,
+    outage: Option<Outage>,
+}
+
+/// Consecutive failed polls of the Coolify API.
+struct Outage {
+    since: u64,
+    failures: u32,
+    last_error: String,
+    notified: bool,
+}
+
+/// Deployments tracked for longer than this are dropped without being
+/// reported, e.g. ones that finished while the relay was down for days.
+const STALE_AFTER_SECS: u64 = 24 * 60 * 60;
+
+/// How often a deployment that left the in-progress list is looked up while
+/// Coolify reports no status, or one it is not known to use, before it is
+/// dropped.
+const MAX_UNKNOWN_STATUS_LOOKUPS: u32 = 10;
+
+/// A deployment seen in the in-progress list, kept until it finishes.
+struct TrackedDeployment {
+    uuid: String,
+    first_seen: u64,
+    deployment: serde_json::Value,
+    unknown_status_lookups: u32,
+}
+
+impl TrackedDeployment {
+    fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.first_seen) > STALE_AFTER_SECS
+    }
+}
+
+/// An entry of the `COOLIFY_INSTANCES` JSON array.
+#[derive(Deserialize)]
+struct InstanceConfig {
+    name: String,
+    url: String,
+    token: String,
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    poll_seconds: Option<u64>,
+}
+
+const DEFAULT_API_ENDPOINT: &str = "api/v1/deployments";
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 10;
+const DEFAULT_ACTIVE_POLL_INTERVAL_SECS: u64 = 3;
+const DEFAULT_MAX_BACKOFF_SECS: u64 = 300;
+const DEFAULT_OUTAGE_NOTIFY_SECS: u64 = 300;
+const MIN_POLL_INTERVAL_SECS: u64 = 1;
+
+#[derive(Clone, Debug, Default)]
+pub struct PollerStatus {
+    pub enabled: bool,
+    pub instances: Vec<InstanceStatus>,
+}
+
+/// The fields the status had before named instances were added, still
+/// serialized at the top level for the instance set with `COOLIFY_API_URL`.
+#[derive(Serialize)]
+struct DefaultInstanceStatus<'a> {
+    poll_interval_secs: u64,
+    last_poll_at: Option<u64>,
+    last_success_at: Option<u64>,
+    last_error: &'a Option<String>,
+    in_progress: &'a [String],
+}
+
+impl Serialize for PollerStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Fields<'a> {
+            enabled: bool,
+            #[serde(flatten)]
+            default: Option<DefaultInstanceStatus<'a>>,
+            instances: &'a [InstanceStatus],
+        }
+
+        let default = self
+            .instances
+            .iter()
+            .find(|instance| instance.name.is_none())
+            .map(|instance| DefaultInstanceStatus {
+                poll_interval_secs: instance.poll_interval_secs,
+                last_poll_at: instance.last_poll_at,
+                last_success_at: instance.last_success_at,
+                last_error: &instance.last_error,
+                in_progress: &instance.in_progress,
+            });
+        Fields {
+            enabled: self.enabled,
+            default,
+            instances: &self.instances,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct InstanceStatus {
+    pub name: Option<String>,
+    pub poll_interval_secs: u64,
+    pub consecutive_failures: u32,
+    pub last_poll_at: Option<u64>,
+    pub last_success_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub in_progress: Vec<String>,
 }
 
 impl DeploymentPollerService {
@@ -23,12 +148,17 @@ impl DeploymentPollerService {
         poll_interval_secs: u64,
     ) -> Self {
         Self {
+            instance: None,
             api_url,
             api_token,
             api_endpoint,
             poll_interval_secs,
+            active_poll_interval_secs: DEFAULT_ACTIVE_POLL_INTERVAL_SECS,
+            max_backoff_secs: DEFAULT_MAX_BACKOFF_SECS,
+            outage_notify_secs: DEFAULT_OUTAGE_NOTIFY_SECS,
             client: reqwest::Client::new(),
             deployments: Vec::new(),
+            outage: None,
         }
     }
 
@@ -51,10 +181,17 @@ impl DeploymentPollerService {
             .await
             .map_err(|e| format!("Failed to call Coolify deployments API: {}", e))?;
 
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            return Err("Coolify deployments API rejected the API token (401).".to_string());
+        }
         let body = response
             .text()
             .await
             .map_err(|e| format!("Failed to read Coolify deployments API response: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("Coolify deployments API responded with {}", status));
+        }
 
         let payload: Vec<serde_json::Value> = serde_json::from_str(&body)
             .map_err(|e| format!("Failed to parse Coolify deployments API response: {}", e))?;
@@ -62,22 +199,88 @@ impl DeploymentPollerService {
         Ok(payload)
     }
 
+    /// Looks up a single deployment, e.g. to read the final status of one that
+    /// left the in-progress list.
+    pub async fn fetch_deployment(&self, uuid: &str) -> Result<serde_json::Value, String> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.deployments_url(), uuid))
+            .bearer_auth(&self.api_token)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call Coolify deployment API: {}", e))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read Coolify deployment API response: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("Coolify deployment API responded with {}", status));
+        }
+
+        serde_json::from_str(&body)
+            .map_err(|e| format!("Failed to parse Coolify deployment API response: {}", e))
+    }
+
+    /// Restores the deployments tracked before a restart, so their start is
+    /// not notified again. Stale entries are expired.
+    pub fn restore(&mut self, history: &HistoryStore) {
+        let saved = match history.polled_deployments(self.instance.as_deref()) {
+            Ok(saved) => saved,
+            Err(error) => {
+                error!("{}", error);
+                return;
+            }
+        };
+
+        let now = unix_timestamp();
+        for saved in saved {
+            let tracked = TrackedDeployment {
+                uuid: saved.uuid,
+                first_seen: saved.first_seen,
+                deployment: saved.payload,
+                unknown_status_lookups: 0,
+            };
+            if tracked.is_stale(now) {
+                debug!(deployment_uuid = %tracked.uuid, "Expired stale deployment");
+                self.forget(history, &tracked.uuid);
+            } else if !self.deployments.iter().any(|d| d.uuid == tracked.uuid) {
+                self.deployments.push(tracked);
+            }
+        }
+
+        if !self.deployments.is_empty() {
+            info!(
+                "Restored {} deployments in progress",
+                self.deployments.len()
+            );
+        }
+    }
+
     pub async fn handle_deployments(
         &mut self,
         state: &Arc<AppState>,
         payload: Vec<serde_json::Value>,
     ) {
-        if payload.is_empty() {
-            self.deployments.clear();
-            return;
-        }
-
         let current_uuids: Vec<String> = payload
             .iter()
             .filter_map(|d| d.get("deployment_uuid").and_then(|v| v.as_str()))
             .map(String::from)
             .collect();
-        self.deployments.retain(|uuid| current_uuids.contains(uuid));
+        let (finished, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.deployments)
+            .into_iter()
+            .partition(|tracked| !current_uuids.contains(&tracked.uuid));
+        self.deployments = running;
+        let now = unix_timestamp();
+        for tracked in finished {
+            if tracked.is_stale(now) {
+                debug!(deployment_uuid = %tracked.uuid, "Expired stale deployment");
+                self.forget(&state.history, &tracked.uuid);
+                continue;
+            }
+            self.report_completion(state, tracked).await;
+        }
 
         for deployment in payload {
             let Some(deployment_uuid) = deployment
@@ -88,14 +291,41 @@ impl DeploymentPollerService {
                 continue;
             };
 
-            if self.deployments.contains(&deployment_uuid) {
+            if self
+                .deployments
+                .iter()
+                .any(|tracked| tracked.uuid == deployment_uuid)
+            {
                 continue;
             }
 
-            self.deployments.push(deployment_uuid);
+            let mut deployment = deployment;
+            if let (Some(instance), serde_json::Value::Object(map)) =
+                (&self.instance, &mut deployment)
+            {
+                map.insert(
+                    "instance".to_string(),
+                    serde_json::Value::String(instance.clone()),
+                );
+            }
+            let tracked = TrackedDeployment {
+                uuid: deployment_uuid,
+                first_seen: unix_timestamp(),
+                deployment: deployment.clone(),
+                unknown_status_lookups: 0,
+            };
+            if let Err(error) = state.history.record_polled_deployment(&PolledDeployment {
+                instance: self.instance.clone(),
+                uuid: tracked.uuid.clone(),
+                first_seen: tracked.first_seen,
+                payload: tracked.deployment.clone(),
+            }) {
+                error!("{}", error);
+            }
+            self.deployments.push(tracked);
 
             let Ok(mut json) = serde_json::to_value(deployment) else {
-                eprintln!("Failed to serialize deployment");
+                error!("Failed to serialize deployment");
                 continue;
             };
 
@@ -106,11 +336,182 @@ impl DeploymentPollerService {
                 );
             }
 
-            services::handle_webhook(State(state.clone()), Json(json)).await;
+            services::accept_webhook(state.clone(), None, json);
         }
     }
 
-    pub fn from_env() -> Result<Option<Self>, &'static str> {
+    /// Emits `deployment_success` or `deployment_failed` for a deployment
+    /// that left the in-progress list, with the time it was seen running.
+    /// Deployments that are still queued or running, or whose lookup failed,
+    /// stay tracked and are looked up again on the next poll.
+    async fn report_completion(&mut self, state: &Arc<AppState>, mut tracked: TrackedDeployment) {
+        let final_status = match self.fetch_deployment(&tracked.uuid).await {
+            Ok(deployment) => deployment
+                .get("status")
+                .and_then(|status| status.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            Err(error) => {
+                warn!(
+                    deployment_uuid = %tracked.uuid,
+                    "Failed to look up finished deployment: {}", error
+                );
+                self.deployments.push(tracked);
+                return;
+            }
+        };
+
+        let event = match final_status.as_str() {
+            "finished" => "deployment_success",
+            "failed" | "cancelled-by-user" => "deployment_failed",
+            "queued" | "in_progress" => {
+                debug!(
+                    deployment_uuid = %tracked.uuid,
+                    status = %final_status,
+                    "Deployment has not finished yet"
+                );
+                self.deployments.push(tracked);
+                return;
+            }
+            _ => {
+                tracked.unknown_status_lookups += 1;
+                if tracked.unknown_status_lookups >= MAX_UNKNOWN_STATUS_LOOKUPS {
+                    warn!(
+                        deployment_uuid = %tracked.uuid,
+                        status = %final_status,
+                        "Dropping deployment with an unknown status"
+                    );
+                    self.forget(&state.history, &tracked.uuid);
+                } else {
+                    debug!(
+                        deployment_uuid = %tracked.uuid,
+                        status = %final_status,
+                        "Deployment has an unknown status"
+                    );
+                    self.deployments.push(tracked);
+                }
+                return;
+            }
+        };
+
+        self.forget(&state.history, &tracked.uuid);
+        let duration_secs = unix_timestamp().saturating_sub(tracked.first_seen);
+        let mut json = tracked.deployment;
+        if let serde_json::Value::Object(ref mut map) = json {
+            map.insert(
+                "event".to_string(),
+                serde_json::Value::String(event.to_string()),
+            );
+            map.insert(
+                "status".to_string(),
+                serde_json::Value::String(final_status),
+            );
+            map.insert(
+                "duration_secs".to_string(),
+                serde_json::Value::from(duration_secs),
+            );
+        }
+
+        services::accept_webhook(state.clone(), None, json);
+    }
+
+    /// How long to wait before the next poll: backing off exponentially after
+    /// failed polls, and polling faster while deployments are in progress.
+    /// Intervals of `0` are treated as one second so the API is never polled
+    /// in a busy loop.
+    pub fn next_delay(&self) -> Duration {
+        let poll_interval_secs = self.poll_interval_secs.max(MIN_POLL_INTERVAL_SECS);
+        let secs = match &self.outage {
+            Some(outage) => {
+                let factor = 2u64.saturating_pow(outage.failures.min(32));
+                poll_interval_secs
+                    .saturating_mul(factor)
+                    .min(self.max_backoff_secs.max(poll_interval_secs))
+            }
+            None if !self.deployments.is_empty() => self
+                .active_poll_interval_secs
+                .clamp(MIN_POLL_INTERVAL_SECS, poll_interval_secs),
+            None => poll_interval_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    /// Records a failed poll. The error is logged when it changes, and an
+    /// outage is notified once the API has failed for `outage_notify_secs`.
+    pub async fn handle_failure(&mut self, state: &Arc<AppState>, error: String) {
+        let now = unix_timestamp();
+        let outage = self.outage.get_or_insert(Outage {
+            since: now,
+            failures: 0,
+            last_error: String::new(),
+            notified: false,
+        });
+        outage.failures += 1;
+        if outage.last_error != error {
+            error!(failures = outage.failures, "{}", error);
+            outage.last_error = error;
+        } else {
+            debug!(failures = outage.failures, "{}", error);
+        }
+
+        let duration_secs = now.saturating_sub(outage.since);
+        if outage.notified
+            || self.outage_notify_secs == 0
+            || duration_secs < self.outage_notify_secs
+        {
+            return;
+        }
+        outage.notified = true;
+        let message = outage.last_error.clone();
+        warn!("Coolify API has failed for {} seconds", duration_secs);
+        self.notify(
+            state,
+            json!({
+                "event": "coolify_api_unreachable",
+                "message": message,
+                "duration_secs": duration_secs,
+            }),
+        )
+        .await;
+    }
+
+    /// Records a successful poll, notifying the recovery of a notified outage.
+    pub async fn handle_success(&mut self, state: &Arc<AppState>) {
+        let Some(outage) = self.outage.take() else {
+            return;
+        };
+
+        let duration_secs = unix_timestamp().saturating_sub(outage.since);
+        info!(
+            failures = outage.failures,
+            "Coolify API is reachable again after {} seconds", duration_secs
+        );
+        if outage.notified {
+            self.notify(
+                state,
+                json!({
+                    "event": "coolify_api_reachable",
+                    "duration_secs": duration_secs,
+                }),
+            )
+            .await;
+        }
+    }
+
+    async fn notify(&self, state: &Arc<AppState>, mut json: serde_json::Value) {
+        if let (Some(instance), serde_json::Value::Object(map)) = (&self.instance, &mut json) {
+            map.insert(
+                "instance".to_string(),
+                serde_json::Value::String(instance.clone()),
+            );
+        }
+
+        services::accept_webhook(state.clone(), None, json);
+    }
+
+    /// Reads the instance set with `COOLIFY_API_URL` and the named instances
+    /// in the `COOLIFY_INSTANCES` JSON array.
+    pub fn from_env() -> Result<Vec<Self>, String> {
         let api_url = env::var("COOLIFY_API_URL")
             .ok()
             .map(|url| url.trim().to_string())
@@ -122,48 +523,184 @@ impl DeploymentPollerService {
         let poll_interval_secs = env::var("COOLIFY_DEPLOYMENT_POLL_SECONDS")
             .ok()
             .and_then(|value| value.parse::<u64>().ok())
-            .unwrap_or(10);
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
         let api_endpoint = env::var("COOLIFY_API_ENDPOINT")
             .ok()
             .map(|endpoint| endpoint.trim().to_string())
             .filter(|endpoint| !endpoint.is_empty())
-            .unwrap_or("api/v1/deployments".to_string());
-
-        match (api_url, api_token, api_endpoint) {
-            (None, _, _) => Ok(None),
-            (Some(_), None, _) => Err(
-                "Environment variable COOLIFY_API_TOKEN is required when COOLIFY_API_URL is set.",
-            ),
-            (Some(api_url), Some(api_token), api_endpoint) => Ok(Some(Self::new(
+            .unwrap_or(DEFAULT_API_ENDPOINT.to_string());
+
+        let mut pollers = match (api_url, api_token, api_endpoint) {
+            (None, _, _) => Vec::new(),
+            (Some(_), None, _) => {
+                return Err(
+                    "Environment variable COOLIFY_API_TOKEN is required when COOLIFY_API_URL is set."
+                        .to_string(),
+                );
+            }
+            (Some(api_url), Some(api_token), api_endpoint) => vec![Self::new(
                 api_url,
                 api_token,
                 api_endpoint,
                 poll_interval_secs,
-            ))),
+            )],
+        };
+
+        if let Ok(json) = env::var("COOLIFY_INSTANCES")
+            && !json.trim().is_empty()
+        {
+            let instances = Self::parse_instances(&json)
+                .map_err(|e| format!("Invalid COOLIFY_INSTANCES: {}", e))?;
+            pollers.extend(instances);
         }
-    }
 
-    pub fn start_polling(state: Arc<AppState>) -> Result<(), &'static str> {
-        let Some(mut deployment_poller) = Self::from_env()? else {
-            return Ok(());
+        let setting = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(default)
         };
+        let active_poll_interval_secs = setting(
+            "COOLIFY_ACTIVE_POLL_SECONDS",
+            DEFAULT_ACTIVE_POLL_INTERVAL_SECS,
+        );
+        let max_backoff_secs =
+            setting("COOLIFY_POLL_MAX_BACKOFF_SECONDS", DEFAULT_MAX_BACKOFF_SECS);
+        let outage_notify_secs =
+            setting("COOLIFY_OUTAGE_NOTIFY_SECONDS", DEFAULT_OUTAGE_NOTIFY_SECS);
+        for poller in pollers.iter_mut() {
+            poller.active_poll_interval_secs = active_poll_interval_secs;
+            poller.max_backoff_secs = max_backoff_secs;
+            poller.outage_notify_secs = outage_notify_secs;
+        }
 
-        println!("Deployment poller initialized");
+        Ok(pollers)
+    }
 
-        tokio::spawn(async move {
-            let state = state.clone();
-            loop {
-                match deployment_poller.check_for_deployments().await {
-                    Ok(payload) => {
-                        deployment_poller.handle_deployments(&state, payload).await;
-                    }
-                    Err(error) => eprintln!("{}", error),
-                }
+    pub fn parse_instances(json: &str) -> Result<Vec<Self>, String> {
+        let configs =
+            serde_json::from_str::<Vec<InstanceConfig>>(json).map_err(|e| e.to_string())?;
 
-                tokio::time::sleep(Duration::from_secs(deployment_poller.poll_interval_secs)).await;
+        let mut names = HashSet::new();
+        let mut pollers = Vec::with_capacity(configs.len());
+        for config in configs {
+            let name = config.name.trim().to_string();
+            if name.is_empty() {
+                return Err("Instance names must not be empty.".to_string());
+            }
+            if !names.insert(name.clone()) {
+                return Err(format!("Duplicate instance name \"{}\".", name));
+            }
+            if config.url.trim().is_empty() || config.token.trim().is_empty() {
+                return Err(format!("Instance \"{}\" needs a url and a token.", name));
             }
-        });
+
+            let mut poller = Self::new(
+                config.url.trim().to_string(),
+                config.token.trim().to_string(),
+                config
+                    .endpoint
+                    .map(|endpoint| endpoint.trim().to_string())
+                    .filter(|endpoint| !endpoint.is_empty())
+                    .unwrap_or(DEFAULT_API_ENDPOINT.to_string()),
+                config.poll_seconds.unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+            );
+            poller.instance = Some(name);
+            pollers.push(poller);
+        }
+
+        Ok(pollers)
+    }
+
+    /// Starts a poller task for every configured Coolify instance.
+    pub fn start_polling(state: Arc<AppState>) -> Result<(), String> {
+        let pollers = Self::from_env()?;
+        if let Ok(mut status) = state.poller_status.write() {
+            status.enabled = !pollers.is_empty();
+            status.instances = pollers
+                .iter()
+                .map(|poller| InstanceStatus {
+                    name: poller.instance.clone(),
+                    poll_interval_secs: poller.poll_interval_secs,
+                    ..InstanceStatus::default()
+                })
+                .collect();
+        }
+
+        for poller in pollers {
+            poller.spawn(state.clone());
+        }
 
         Ok(())
     }
+
+    fn spawn(mut self, state: Arc<AppState>) {
+        let span = info_span!("poller", instance = tracing::field::Empty);
+        if let Some(instance) = &self.instance {
+            span.record("instance", instance.as_str());
+        }
+
+        span.in_scope(|| {
+            info!("Deployment poller initialized");
+            self.restore(&state.history);
+        });
+
+        tokio::spawn(
+            async move {
+                loop {
+                    let result = self.check_for_deployments().await;
+                    let error = result.as_ref().err().cloned();
+                    metrics::POLLER_CYCLES.inc();
+
+                    match result {
+                        Ok(payload) => {
+                            self.handle_success(&state).await;
+                            self.handle_deployments(&state, payload).await;
+                        }
+                        Err(error) => {
+                            metrics::POLLER_ERRORS.inc();
+                            self.handle_failure(&state, error).await;
+                        }
+                    }
+
+                    let in_progress = self
+                        .deployments
+                        .iter()
+                        .map(|tracked| tracked.uuid.clone())
+                        .collect::<Vec<String>>();
+                    let failures = self.outage.as_ref().map_or(0, |outage| outage.failures);
+                    self.update_status(&state, |status| {
+                        let now = Some(unix_timestamp());
+                        status.consecutive_failures = failures;
+                        status.last_poll_at = now;
+                        if error.is_none() {
+                            status.last_success_at = now;
+                            status.in_progress = in_progress;
+                        }
+                        status.last_error = error;
+                    });
+
+                    tokio::time::sleep(self.next_delay()).await;
+                }
+            }
+            .instrument(span),
+        );
+    }
+
+    fn update_status(&self, state: &Arc<AppState>, update: impl FnOnce(&mut InstanceStatus)) {
+        if let Ok(mut status) = state.poller_status.write()
+            && let Some(instance) = status
+                .instances
+                .iter_mut()
+                .find(|instance| instance.name == self.instance)
+        {
+            update(instance);
+        }
+    }
+
+    fn forget(&self, history: &HistoryStore, uuid: &str) {
+        if let Err(error) = history.remove_polled_deployment(self.instance.as_deref(), uuid) {
+            error!("{}", error);
+        }
+    }
 }
@@ -0,0 +1,113 @@
+use std::env;
+
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventFamily},
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+    utils::{rfc3339, truncate, unix_timestamp},
+};
+
+/// Posts notifications to a Discord channel webhook as embeds.
+pub struct DiscordService {
+    pub webhook_url: String,
+    pub username: Option<String>,
+    pub filter: EventFilter,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+impl DiscordService {
+    pub fn new(webhook_url: String, client: reqwest::Client) -> Self {
+        Self {
+            webhook_url,
+            username: None,
+            filter: EventFilter::default(),
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `DISCORD_WEBHOOK_URL` and the optional `DISCORD_USERNAME` and
+    /// `DISCORD_FILTER`. Returns `None` when Discord is not configured.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(webhook_url) = env::var("DISCORD_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            username: env::var("DISCORD_USERNAME")
+                .ok()
+                .filter(|name| !name.trim().is_empty()),
+            filter: EventFilter::from_env("DISCORD_FILTER")?,
+            ..Self::new(webhook_url, client)
+        }))
+    }
+
+    /// Builds the webhook message with a single embed, respecting Discord's
+    /// length limits.
+    pub fn message(&self, payload: &WebhookPayload, notification: &Notification) -> Value {
+        let color = match event_parser::event_family(payload.event.as_deref().unwrap_or("")) {
+            EventFamily::Success => 0x2ecc71,
+            EventFamily::Failure => 0xe74c3c,
+            EventFamily::Info => 0x3498db,
+        };
+        let fields = event_parser::payload_fields(payload)
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": truncate(&value, 1024), "inline": true }))
+            .collect::<Vec<_>>();
+
+        let mut embed = json!({
+            "title": truncate(&notification.title, 256),
+            "description": truncate(&notification.body, 4096),
+            "color": color,
+            "fields": fields,
+            "timestamp": rfc3339(unix_timestamp()),
+        });
+        if let Some(link) = event_parser::links(payload).into_iter().next() {
+            embed["url"] = json!(link.url);
+        }
+
+        let mut message = json!({ "embeds": [embed] });
+        if let Some(username) = &self.username {
+            message["username"] = json!(username);
+        }
+        message
+    }
+
+    /// Posts the notification unless the filter rejects the webhook, in
+    /// which case `Ok(false)` is returned. Rate limited requests are retried
+    /// after Discord's `retry_after`.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        if !self.filter.matches(payload) {
+            debug!("Webhook filtered out for Discord");
+            return Ok(false);
+        }
+
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let message = self.message(&webhook_payload, notification).to_string();
+
+        output::send("discord", &self.retry_policy, || {
+            self.client
+                .post(&self.webhook_url)
+                .header("Content-Type", "application/json")
+                .body(message.clone())
+        })
+        .await?;
+
+        Ok(true)
+    }
+}
+
+chat_sink!(DiscordService, "discord");
@@ -0,0 +1,192 @@
+use std::{collections::HashSet, env, sync::Arc};
+
+use axum::http::{HeaderMap, header};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    Notification,
+    services::{
+        repeater::{RepeaterTarget, WebhookRepeaterService},
+        sink::NotificationSink,
+    },
+    template::TemplateContext,
+    utils::constant_time_eq,
+};
+
+/// A named webhook endpoint served at `/hooks/{name}`, so a single relay can
+/// serve several Coolify teams or instances with their own settings.
+pub struct WebhookEndpoint {
+    pub name: String,
+    /// Required as `Authorization: Bearer <secret>` or `?secret=<secret>`.
+    pub secret: Option<String>,
+    /// Push tokens and device groups that receive this endpoint's events
+    /// instead of every token.
+    pub devices: Vec<String>,
+    pub template: Option<NotificationTemplate>,
+    /// Forwards this endpoint's events instead of the global repeater.
+    pub repeater: Option<Arc<WebhookRepeaterService>>,
+}
+
+/// Overrides for the notification, rendered like repeater templates, see
+/// [`TemplateContext::render_str`].
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct NotificationTemplate {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EndpointConfig {
+    name: String,
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    devices: Vec<String>,
+    #[serde(default)]
+    template: Option<NotificationTemplate>,
+    #[serde(default)]
+    repeaters: Vec<RepeaterTarget>,
+}
+
+impl WebhookEndpoint {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            secret: None,
+            devices: Vec::new(),
+            template: None,
+            repeater: None,
+        }
+    }
+
+    /// Reads the `WEBHOOK_ENDPOINTS` JSON array. Repeaters of an endpoint use
+    /// the retry and timeout settings of `repeater`.
+    pub fn from_env(
+        client: &reqwest::Client,
+        repeater: &WebhookRepeaterService,
+    ) -> Result<Vec<Arc<WebhookEndpoint>>, String> {
+        let Ok(json) = env::var("WEBHOOK_ENDPOINTS") else {
+            return Ok(Vec::new());
+        };
+        if json.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let endpoints = Self::parse(&json, client, repeater)
+            .map_err(|e| format!("Invalid WEBHOOK_ENDPOINTS: {}", e))?;
+        Ok(endpoints.into_iter().map(Arc::new).collect())
+    }
+
+    pub fn parse(
+        json: &str,
+        client: &reqwest::Client,
+        repeater: &WebhookRepeaterService,
+    ) -> Result<Vec<WebhookEndpoint>, String> {
+        let configs =
+            serde_json::from_str::<Vec<EndpointConfig>>(json).map_err(|e| e.to_string())?;
+
+        let mut names = HashSet::new();
+        let mut endpoints = Vec::with_capacity(configs.len());
+        for config in configs {
+            if config.name.is_empty()
+                || !config
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!("Invalid endpoint name \"{}\".", config.name));
+            }
+            if !names.insert(config.name.clone()) {
+                return Err(format!("Duplicate endpoint name \"{}\".", config.name));
+            }
+            for target in config.repeaters.iter() {
+                target.validate()?;
+            }
+
+            let endpoint_repeater = (!config.repeaters.is_empty()).then(|| {
+                let mut endpoint_repeater =
+                    WebhookRepeaterService::new(config.repeaters, client.clone());
+                endpoint_repeater.retry_policy = repeater.retry_policy.clone();
+                endpoint_repeater.timeout = repeater.timeout;
+                Arc::new(endpoint_repeater)
+            });
+            endpoints.push(WebhookEndpoint {
+                name: config.name,
+                secret: config.secret.filter(|secret| !secret.is_empty()),
+                devices: config.devices,
+                template: config.template,
+                repeater: endpoint_repeater,
+            });
+        }
+
+        Ok(endpoints)
+    }
+
+    pub fn is_authorized(&self, headers: &HeaderMap, query_secret: Option<&str>) -> bool {
+        let Some(secret) = &self.secret else {
+            return true;
+        };
+
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        [bearer, query_secret]
+            .into_iter()
+            .flatten()
+            .any(|candidate| constant_time_eq(candidate.as_bytes(), secret.as_bytes()))
+    }
+
+    /// Adds the endpoint name to the payload, so it is part of the stored
+    /// event, the push notification `data` and forwarded webhooks.
+    pub fn tag(&self, payload: &mut Value) {
+        if let Some(fields) = payload.as_object_mut() {
+            fields.insert("endpoint".to_string(), Value::String(self.name.clone()));
+        }
+    }
+
+    /// Applies the endpoint's template to a parsed notification.
+    pub fn render(&self, payload: &Value, notification: Notification) -> Notification {
+        let Some(template) = &self.template else {
+            return notification;
+        };
+
+        let context = TemplateContext {
+            notification: &notification,
+            payload,
+        };
+        Notification {
+            title: template
+                .title
+                .as_deref()
+                .map(|title| context.render_str(title))
+                .unwrap_or_else(|| notification.title.clone()),
+            body: template
+                .body
+                .as_deref()
+                .map(|body| context.render_str(body))
+                .unwrap_or_else(|| notification.body.clone()),
+        }
+    }
+
+    /// The sink to use in place of a global one for this endpoint's events.
+    pub fn sink(&self, sink: &Arc<dyn NotificationSink>) -> Arc<dyn NotificationSink> {
+        match &self.repeater {
+            Some(repeater) if sink.name() == "repeater" => repeater.clone(),
+            _ => sink.clone(),
+        }
+    }
+
+    /// The targets to deliver to when a route selects every target of
+    /// `output`: the endpoint's devices for Expo.
+    pub fn targets(&self, output: &str) -> Vec<String> {
+        match output {
+            "expo" => self.devices.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
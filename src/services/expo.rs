@@ -1,5 +1,18 @@
-use serde::Serialize;
+use std::{collections::HashSet, sync::Mutex, time::Instant};
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
 use serde_json::{Error, json};
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+use crate::{
+    Notification, metrics,
+    services::{
+        groups::DeviceGroups,
+        sink::{NotificationSink, SinkReport},
+    },
+    utils::unix_timestamp,
+};
 
 #[derive(Debug)]
 pub struct ExpoNotification<T> {
@@ -11,7 +24,30 @@ pub struct ExpoNotification<T> {
 pub struct ExpoService {
     pub expo_push_tokens: Vec<String>,
     pub expo_push_url: String,
+    /// Groups that routes and the updater can send to instead of every token.
+    pub groups: DeviceGroups,
     client: reqwest::Client,
+    dead_tokens: Mutex<HashSet<String>>,
+    status: Mutex<ExpoStatus>,
+}
+
+/// Reachability of the Expo push API. Rejected tickets do not count as errors
+/// here, only requests that did not get a successful HTTP response.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ExpoStatus {
+    pub last_attempt_at: Option<u64>,
+    pub last_success_at: Option<u64>,
+    pub last_error: Option<String>,
+    /// First failed request since the last successful one.
+    pub failing_since: Option<u64>,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExpoDelivery {
+    pub token: String,
+    pub success: bool,
+    pub detail: Option<String>,
 }
 
 impl<T: Serialize> ExpoNotification<T> {
@@ -38,37 +74,247 @@ impl ExpoService {
         Self {
             expo_push_tokens,
             expo_push_url,
+            groups: DeviceGroups::default(),
             client,
+            dead_tokens: Mutex::new(HashSet::new()),
+            status: Mutex::new(ExpoStatus::default()),
         }
     }
 
-    pub async fn send_notification<T: Serialize>(&self, notification: ExpoNotification<T>) {
-        for token in self.expo_push_tokens.iter() {
-            let Ok(body) = notification.to_json_with_token(token) else {
-                eprintln!("Failed to serialize notification for token: {:?}", token);
-                continue;
-            };
-            println!("Sending Expo notification with title: {:?}", notification.title);
-
-            let res = self
-                .client
-                .post(&self.expo_push_url)
-                .header("Content-Type", "application/json")
-                .header(
-                    "User-Agent",
-                    format!("{} v{}", ExpoService::PACKAGE_NAME, ExpoService::VERSION),
-                )
-                .body(body)
-                .send()
+    pub fn status(&self) -> ExpoStatus {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    /// Tokens Expo reported as `DeviceNotRegistered` on their latest push.
+    pub fn dead_tokens(&self) -> Vec<String> {
+        let mut tokens = self
+            .dead_tokens
+            .lock()
+            .map(|tokens| tokens.iter().cloned().collect::<Vec<String>>())
+            .unwrap_or_default();
+        tokens.sort();
+        tokens
+    }
+
+    pub async fn send_notification<T: Serialize>(
+        &self,
+        notification: ExpoNotification<T>,
+    ) -> Vec<ExpoDelivery> {
+        self.send_to_tokens(&self.expo_push_tokens, &notification)
+            .await
+    }
+
+    /// Sends to the members of the named groups only. A token in several of
+    /// the groups is notified once.
+    pub async fn send_notification_to_groups<T: Serialize>(
+        &self,
+        groups: &[String],
+        notification: ExpoNotification<T>,
+    ) -> Vec<ExpoDelivery> {
+        let (tokens, unknown) = self.groups.members(groups);
+        if !unknown.is_empty() {
+            warn!(groups = ?unknown, "Unknown device groups");
+        }
+
+        self.send_to_tokens(&tokens, &notification).await
+    }
+
+    async fn send_to_tokens<T: Serialize>(
+        &self,
+        tokens: &[String],
+        notification: &ExpoNotification<T>,
+    ) -> Vec<ExpoDelivery> {
+        let mut deliveries = Vec::with_capacity(tokens.len());
+
+        for (index, token) in tokens.iter().enumerate() {
+            let delivery = self
+                .send_to_token(token, notification)
+                .instrument(info_span!("expo_send", token_index = index))
                 .await;
+            deliveries.push(delivery);
+        }
+
+        deliveries
+    }
+
+    async fn send_to_token<T: Serialize>(
+        &self,
+        token: &str,
+        notification: &ExpoNotification<T>,
+    ) -> ExpoDelivery {
+        let Ok(body) = notification.to_json_with_token(token) else {
+            error!("Failed to serialize notification");
+            metrics::EXPO_SENDS
+                .with_label_values(&["serialize_error"])
+                .inc();
+            return ExpoDelivery {
+                token: token.to_string(),
+                success: false,
+                detail: Some("Failed to serialize notification.".to_string()),
+            };
+        };
+        info!(title = %notification.title, "Sending Expo notification");
+
+        let started_at = Instant::now();
+        let res = self
+            .client
+            .post(&self.expo_push_url)
+            .header("Content-Type", "application/json")
+            .header(
+                "User-Agent",
+                format!("{} v{}", ExpoService::PACKAGE_NAME, ExpoService::VERSION),
+            )
+            .body(body)
+            .send()
+            .await;
+
+        let (delivery, outcome) = match res {
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                debug!(status = status.as_u16(), body = %text, "Expo response");
+
+                let ticket_error = Self::ticket_error(&text);
+                self.mark_dead_token(
+                    token,
+                    ticket_error.as_deref() == Some("DeviceNotRegistered"),
+                );
+
+                self.record_status(
+                    (!status.is_success()).then(|| format!("Expo responded with {}", status)),
+                );
 
-            match res {
-                Ok(response) => println!(
-                    "Expo response: {:?}",
-                    response.text().await.unwrap_or_default()
-                ),
-                Err(e) => eprintln!("Failed to send Expo notification: {:?}", e),
+                let success = status.is_success() && ticket_error.is_none();
+                if !success {
+                    warn!(
+                        status = status.as_u16(),
+                        error = ticket_error.as_deref().unwrap_or("http_error"),
+                        "Expo rejected notification"
+                    );
+                }
+                let delivery = ExpoDelivery {
+                    token: token.to_string(),
+                    success,
+                    detail: Some(text),
+                };
+                (delivery, if success { "success" } else { "rejected" })
+            }
+            Err(e) => {
+                error!("Failed to send Expo notification: {}", e);
+                self.record_status(Some(e.to_string()));
+                let delivery = ExpoDelivery {
+                    token: token.to_string(),
+                    success: false,
+                    detail: Some(e.to_string()),
+                };
+                (delivery, "request_error")
+            }
+        };
+
+        metrics::EXPO_SENDS.with_label_values(&[outcome]).inc();
+        metrics::EXPO_SEND_DURATION
+            .with_label_values(&[outcome])
+            .observe(started_at.elapsed().as_secs_f64());
+        delivery
+    }
+
+    /// Expo answers `200 OK` even when a push ticket is rejected, so the ticket
+    /// status in the body has to be checked as well. Returns the error code of a
+    /// rejected ticket, or `"error"` when Expo did not include one.
+    fn ticket_error(body: &str) -> Option<String> {
+        let value = serde_json::from_str::<serde_json::Value>(body).ok()?;
+        if value.pointer("/data/status")?.as_str()? != "error" {
+            return None;
+        }
+
+        let code = value
+            .pointer("/data/details/error")
+            .and_then(|code| code.as_str())
+            .unwrap_or("error");
+        Some(code.to_string())
+    }
+
+    fn record_status(&self, error: Option<String>) {
+        let Ok(mut status) = self.status.lock() else {
+            return;
+        };
+
+        let now = Some(unix_timestamp());
+        status.last_attempt_at = now;
+        match error {
+            Some(error) => {
+                status.last_error = Some(error);
+                status.failing_since = status.failing_since.or(now);
+                status.consecutive_failures += 1;
+            }
+            None => {
+                status.last_success_at = now;
+                status.last_error = None;
+                status.failing_since = None;
+                status.consecutive_failures = 0;
             }
         }
     }
+
+    fn mark_dead_token(&self, token: &str, dead: bool) {
+        let Ok(mut dead_tokens) = self.dead_tokens.lock() else {
+            return;
+        };
+
+        if dead {
+            dead_tokens.insert(token.to_string());
+        } else {
+            dead_tokens.remove(token);
+        }
+    }
+}
+
+impl NotificationSink for ExpoService {
+    fn name(&self) -> &'static str {
+        "expo"
+    }
+
+    fn deliver<'a>(
+        &'a self,
+        payload: &'a serde_json::Value,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, SinkReport> {
+        Box::pin(async move {
+            let deliveries = self
+                .send_notification(ExpoNotification {
+                    title: notification.title.clone(),
+                    body: notification.body.clone(),
+                    data: payload,
+                })
+                .await;
+
+            SinkReport::from_deliveries(deliveries)
+        })
+    }
+
+    /// Routes select device groups, e.g. `expo:oncall`.
+    fn deliver_to<'a>(
+        &'a self,
+        payload: &'a serde_json::Value,
+        notification: &'a Notification,
+        targets: &'a [String],
+    ) -> BoxFuture<'a, SinkReport> {
+        Box::pin(async move {
+            let deliveries = self
+                .send_notification_to_groups(
+                    targets,
+                    ExpoNotification {
+                        title: notification.title.clone(),
+                        body: notification.body.clone(),
+                        data: payload,
+                    },
+                )
+                .await;
+
+            SinkReport::from_deliveries(deliveries)
+        })
+    }
 }
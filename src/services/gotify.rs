@@ -0,0 +1,113 @@
+use std::env;
+
+use serde_json::{Value, json};
+use tracing::{debug, error};
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventSeverity},
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+};
+
+/// Posts notifications to a Gotify server as messages of an application.
+pub struct GotifyService {
+    pub server_url: String,
+    pub app_token: String,
+    pub filter: EventFilter,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+impl GotifyService {
+    pub fn new(server_url: String, app_token: String, client: reqwest::Client) -> Self {
+        Self {
+            server_url,
+            app_token,
+            filter: EventFilter::default(),
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `GOTIFY_URL`, `GOTIFY_APP_TOKEN` and the optional
+    /// `GOTIFY_FILTER`. Returns `None` when Gotify is not fully configured.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(server_url) = env::var("GOTIFY_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+        else {
+            return Ok(None);
+        };
+        let Some(app_token) = env::var("GOTIFY_APP_TOKEN")
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+        else {
+            error!("GOTIFY_APP_TOKEN is required when GOTIFY_URL is set.");
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            filter: EventFilter::from_env("GOTIFY_FILTER")?,
+            ..Self::new(server_url, app_token, client)
+        }))
+    }
+
+    /// Builds the message. Severity maps to the Gotify priority, and the raw
+    /// webhook is attached under the `coolify::webhook` extras namespace.
+    pub fn message(payload: &Value, notification: &Notification) -> Value {
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let event = webhook_payload.event.as_deref().unwrap_or("");
+        let priority = match event_parser::event_severity(event) {
+            EventSeverity::Info => 4,
+            EventSeverity::Warning => 7,
+            EventSeverity::Critical => 10,
+        };
+
+        let mut extras = json!({
+            "client::display": { "contentType": "text/plain" },
+            "coolify::webhook": payload,
+        });
+        if let Some(link) = event_parser::links(&webhook_payload).into_iter().next() {
+            extras["client::notification"] = json!({ "click": { "url": link.url } });
+        }
+
+        json!({
+            "title": notification.title,
+            "message": notification.body,
+            "priority": priority,
+            "extras": extras,
+        })
+    }
+
+    /// Posts the notification unless the filter rejects the webhook, in
+    /// which case `Ok(false)` is returned.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        if !self.filter.matches(payload) {
+            debug!("Webhook filtered out for Gotify");
+            return Ok(false);
+        }
+
+        let message = Self::message(payload, notification).to_string();
+        let url = format!("{}/message", self.server_url.trim_end_matches('/'));
+
+        output::send("gotify", &self.retry_policy, || {
+            self.client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .header("X-Gotify-Key", &self.app_token)
+                .body(message.clone())
+        })
+        .await?;
+
+        Ok(true)
+    }
+}
+
+chat_sink!(GotifyService, "gotify");
@@ -0,0 +1,179 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    sync::RwLock,
+};
+
+use serde::Serialize;
+
+use crate::utils::is_expo_push_token;
+
+/// Named sets of push tokens, e.g. `oncall` or `frontend`, that routing rules
+/// and the updater can target. A token may belong to several groups.
+///
+/// Groups come from `EXPO_DEVICE_GROUPS` and from the admin API. Members added
+/// through the API are stored in the history database and kept next to the
+/// configured ones, which can only be changed in the configuration.
+#[derive(Default)]
+pub struct DeviceGroups {
+    configured: BTreeMap<String, BTreeSet<String>>,
+    managed: RwLock<BTreeMap<String, BTreeSet<String>>>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceGroup {
+    pub name: String,
+    pub members: Vec<String>,
+    /// Members from `EXPO_DEVICE_GROUPS`, which the admin API cannot remove.
+    pub configured: Vec<String>,
+}
+
+impl DeviceGroups {
+    pub fn new(configured: BTreeMap<String, Vec<String>>) -> Self {
+        Self {
+            configured: configured
+                .into_iter()
+                .map(|(name, members)| (name, members.into_iter().collect()))
+                .collect(),
+            managed: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Reads the `EXPO_DEVICE_GROUPS` JSON object of group names to tokens.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(json) = env::var("EXPO_DEVICE_GROUPS") else {
+            return Ok(Self::default());
+        };
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+
+        let configured =
+            Self::parse(&json).map_err(|e| format!("Invalid EXPO_DEVICE_GROUPS: {}", e))?;
+        Ok(Self::new(configured))
+    }
+
+    pub fn parse(json: &str) -> Result<BTreeMap<String, Vec<String>>, String> {
+        let groups: BTreeMap<String, Vec<String>> =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+        for (name, members) in groups.iter() {
+            Self::validate(name, members)?;
+        }
+        Ok(groups)
+    }
+
+    /// Group names are limited to letters, digits, `-` and `_` so they can be
+    /// used in routing destinations and URLs.
+    pub fn validate(name: &str, members: &[String]) -> Result<(), String> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("Invalid group name \"{}\".", name));
+        }
+        if let Some(member) = members.iter().find(|token| !is_expo_push_token(token)) {
+            return Err(format!(
+                "Group {} has an invalid push token \"{}\".",
+                name, member
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the members managed through the admin API, e.g. with the
+    /// groups stored in the database at startup.
+    pub fn load(&self, managed: BTreeMap<String, Vec<String>>) {
+        if let Ok(mut groups) = self.managed.write() {
+            *groups = managed
+                .into_iter()
+                .map(|(name, members)| (name, members.into_iter().collect()))
+                .collect();
+        }
+    }
+
+    pub fn set(&self, name: &str, members: Vec<String>) {
+        if let Ok(mut groups) = self.managed.write() {
+            if members.is_empty() {
+                groups.remove(name);
+            } else {
+                groups.insert(name.to_string(), members.into_iter().collect());
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<DeviceGroup> {
+        let configured = self.configured.get(name);
+        let managed = self
+            .managed
+            .read()
+            .ok()
+            .and_then(|groups| groups.get(name).cloned());
+        if configured.is_none() && managed.is_none() {
+            return None;
+        }
+
+        let configured = configured.cloned().unwrap_or_default();
+        let members = configured
+            .iter()
+            .chain(managed.iter().flatten())
+            .cloned()
+            .collect::<BTreeSet<String>>();
+        Some(DeviceGroup {
+            name: name.to_string(),
+            members: members.into_iter().collect(),
+            configured: configured.into_iter().collect(),
+        })
+    }
+
+    pub fn list(&self) -> Vec<DeviceGroup> {
+        let mut names = self
+            .configured
+            .keys()
+            .cloned()
+            .collect::<BTreeSet<String>>();
+        if let Ok(groups) = self.managed.read() {
+            names.extend(groups.keys().cloned());
+        }
+
+        names.iter().filter_map(|name| self.get(name)).collect()
+    }
+
+    /// Fails when one of `names` is neither a push token nor a known group,
+    /// e.g. an `expo:<group>` routing destination with a typo.
+    pub fn check(&self, names: &[String]) -> Result<(), String> {
+        let (_, unknown) = self.members(names);
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(format!("Unknown device groups: {}.", unknown.join(", ")))
+    }
+
+    /// The members of the named groups, without duplicates. Push tokens in
+    /// `names` stand for themselves. Unknown groups are returned separately
+    /// so callers can report them.
+    pub fn members(&self, names: &[String]) -> (Vec<String>, Vec<String>) {
+        let mut members: Vec<String> = Vec::new();
+        let mut unknown: Vec<String> = Vec::new();
+
+        for name in names {
+            if is_expo_push_token(name) {
+                if !members.contains(name) {
+                    members.push(name.clone());
+                }
+                continue;
+            }
+            let Some(group) = self.get(name) else {
+                unknown.push(name.clone());
+                continue;
+            };
+            for member in group.members {
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+        }
+
+        (members, unknown)
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, env, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{metrics, state::AppState, utils::unix_timestamp};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Degraded,
+    Disabled,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HealthCheck {
+    pub status: CheckStatus,
+    pub critical: bool,
+    pub message: Option<String>,
+    pub details: serde_json::Value,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HealthReport {
+    pub status: CheckStatus,
+    pub checks: BTreeMap<&'static str, HealthCheck>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HealthQuery {
+    pub verbose: Option<String>,
+}
+
+impl HealthCheck {
+    fn new(status: CheckStatus, critical: bool, details: serde_json::Value) -> Self {
+        Self {
+            status,
+            critical,
+            message: None,
+            details,
+        }
+    }
+
+    fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+impl HealthReport {
+    const DEFAULT_MAX_OUTBOX: i64 = 100;
+    const DEFAULT_EXPO_MAX_FAILURE_SECS: u64 = 300;
+
+    pub fn from_state(state: &AppState) -> Self {
+        let mut checks = BTreeMap::new();
+        checks.insert("expo", expo_check(state));
+        checks.insert("poller", poller_check(state));
+        checks.insert("repeaters", repeaters_check(state));
+        checks.insert("outbox", outbox_check());
+        checks.insert("database", database_check(state));
+        checks.insert("config", config_check(state));
+
+        let degraded = checks
+            .values()
+            .any(|check| check.critical && check.status == CheckStatus::Degraded);
+
+        Self {
+            status: if degraded {
+                CheckStatus::Degraded
+            } else {
+                CheckStatus::Ok
+            },
+            checks,
+        }
+    }
+
+    fn into_response(self) -> Response {
+        let status = match self.status {
+            CheckStatus::Degraded => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::OK,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+pub async fn show_ready(State(state): State<Arc<AppState>>) -> Response {
+    HealthReport::from_state(&state).into_response()
+}
+
+pub async fn show_health(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HealthQuery>,
+) -> Response {
+    if query.verbose.is_none() {
+        return (StatusCode::OK, "OK").into_response();
+    }
+
+    HealthReport::from_state(&state).into_response()
+}
+
+/// Expo is only critical once pushes have kept failing, with no success in
+/// between, for `READY_EXPO_MAX_FAILURE_SECONDS`. A single failed push, e.g. a
+/// timeout, is reported as degraded without taking the relay out of rotation.
+fn expo_check(state: &AppState) -> HealthCheck {
+    let status = state.expo.status();
+    let max_failure_secs = env::var("READY_EXPO_MAX_FAILURE_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(HealthReport::DEFAULT_EXPO_MAX_FAILURE_SECS);
+    let critical = status.consecutive_failures > 1
+        && status
+            .failing_since
+            .is_some_and(|since| unix_timestamp().saturating_sub(since) >= max_failure_secs);
+    let details = json!(status);
+
+    match status.last_error {
+        Some(error) => {
+            HealthCheck::new(CheckStatus::Degraded, critical, details).with_message(error)
+        }
+        None => HealthCheck::new(CheckStatus::Ok, critical, details),
+    }
+}
+
+/// The poller is degraded when it has not read the Coolify API of an
+/// instance successfully for three poll intervals, e.g. because the API token
+/// was revoked.
+fn poller_check(state: &AppState) -> HealthCheck {
+    let Ok(status) = state.poller_status.read().map(|status| status.clone()) else {
+        return HealthCheck::new(CheckStatus::Degraded, true, json!(null))
+            .with_message("Poller status is unavailable.");
+    };
+    let details = json!(status);
+
+    if !status.enabled {
+        return HealthCheck::new(CheckStatus::Disabled, true, details);
+    }
+
+    let errors = status
+        .instances
+        .iter()
+        .filter_map(|instance| {
+            let stale_after = (instance.poll_interval_secs * 3).max(60);
+            let since = instance.last_success_at.or(instance.last_poll_at);
+            let stale = match since {
+                Some(since) => unix_timestamp().saturating_sub(since) > stale_after,
+                None => false,
+            };
+            if !stale && (instance.last_success_at.is_some() || instance.last_error.is_none()) {
+                return None;
+            }
+
+            let message = instance
+                .last_error
+                .clone()
+                .unwrap_or("The Coolify API has not been polled successfully.".to_string());
+            Some(match &instance.name {
+                Some(name) => format!("{}: {}", name, message),
+                None => message,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if !errors.is_empty() {
+        return HealthCheck::new(CheckStatus::Degraded, true, details)
+            .with_message(errors.join("; "));
+    }
+
+    HealthCheck::new(CheckStatus::Ok, true, details)
+}
+
+/// Targets are listed by name or position, since the report is served
+/// without authentication and URLs can carry tokens.
+fn repeaters_check(state: &AppState) -> HealthCheck {
+    let statuses = state.repeater.labelled_statuses();
+    if statuses.is_empty() {
+        return HealthCheck::new(CheckStatus::Disabled, false, json!([]));
+    }
+
+    let failing = statuses
+        .iter()
+        .filter(|(_, status)| status.last_error.is_some())
+        .count();
+    let details = statuses
+        .iter()
+        .map(|(target, status)| {
+            json!({
+                "target": target,
+                "last_attempt_at": status.last_attempt_at,
+                "last_success_at": status.last_success_at,
+                "last_error": status.last_error,
+                "last_duration_ms": status.last_duration_ms,
+            })
+        })
+        .collect::<Vec<_>>();
+    let check = HealthCheck::new(
+        if failing == 0 {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Degraded
+        },
+        false,
+        json!(details),
+    );
+
+    if failing == 0 {
+        check
+    } else {
+        check.with_message(format!("{} repeater target(s) are unreachable.", failing))
+    }
+}
+
+fn outbox_check() -> HealthCheck {
+    let depth = metrics::OUTBOX_DEPTH.get();
+    let max = env::var("READY_MAX_OUTBOX")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(HealthReport::DEFAULT_MAX_OUTBOX);
+    let details = json!({ "depth": depth, "max": max });
+
+    if depth > max {
+        return HealthCheck::new(CheckStatus::Degraded, true, details)
+            .with_message(format!("{} deliveries are waiting to be sent.", depth));
+    }
+
+    HealthCheck::new(CheckStatus::Ok, true, details)
+}
+
+fn database_check(state: &AppState) -> HealthCheck {
+    match state.history.ping() {
+        Ok(()) => HealthCheck::new(CheckStatus::Ok, true, json!(null)),
+        Err(error) => {
+            HealthCheck::new(CheckStatus::Degraded, true, json!(null)).with_message(error)
+        }
+    }
+}
+
+fn config_check(state: &AppState) -> HealthCheck {
+    let tokens = state.expo.expo_push_tokens.len();
+    let dead_tokens = state.expo.dead_tokens().len();
+    let details = json!({
+        "expo_push_tokens": tokens,
+        "dead_tokens": dead_tokens,
+        "repeater_urls": state.repeater.targets.len(),
+        "sinks": state.sinks.iter().map(|sink| sink.name()).collect::<Vec<_>>(),
+        "routing_rules": state.routes.rules.len(),
+        "endpoints": state.endpoints.iter().map(|endpoint| endpoint.name.as_str()).collect::<Vec<_>>(),
+        "dashboard": state.admin.is_some(),
+    });
+
+    if tokens > 0 && dead_tokens == tokens {
+        return HealthCheck::new(CheckStatus::Degraded, true, details)
+            .with_message("Every Expo push token is no longer registered.");
+    }
+
+    HealthCheck::new(CheckStatus::Ok, true, details)
+}
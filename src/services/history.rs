@@ -0,0 +1,598 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter, types::Value as SqlValue};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    Notification, WebhookPayload,
+    services::{expo::ExpoDelivery, repeater::ForwardOutcome},
+    utils::unix_timestamp,
+};
+
+pub struct HistoryStore {
+    pub retention_days: u64,
+    conn: Mutex<Connection>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Partial,
+    Failed,
+    /// No sink was selected for the event, e.g. a route sent it nowhere.
+    Skipped,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EventRecord {
+    pub id: i64,
+    pub received_at: i64,
+    pub event: String,
+    pub server_name: Option<String>,
+    pub application_name: Option<String>,
+    pub project: Option<String>,
+    pub payload: serde_json::Value,
+    pub notification: Notification,
+    pub delivery_status: DeliveryStatus,
+    pub deliveries: Vec<ExpoDelivery>,
+}
+
+/// A repeater delivery that still failed after every retry.
+#[derive(Serialize, Clone, Debug)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub created_at: i64,
+    pub target_url: String,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub replayed_at: Option<i64>,
+}
+
+/// A deployment the poller has notified as started and not yet as finished.
+#[derive(Clone, Debug)]
+pub struct PolledDeployment {
+    /// The Coolify instance it was seen on, `None` for `COOLIFY_API_URL`.
+    pub instance: Option<String>,
+    pub uuid: String,
+    pub first_seen: u64,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct DeadLetterQuery {
+    pub target: Option<String>,
+    pub include_replayed: Option<bool>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct EventQuery {
+    pub event: Option<String>,
+    pub server: Option<String>,
+    pub app: Option<String>,
+    pub status: Option<DeliveryStatus>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EventPage {
+    pub events: Vec<EventRecord>,
+    pub total: u64,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Partial => "partial",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Skipped => "skipped",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "delivered" => DeliveryStatus::Delivered,
+            "partial" => DeliveryStatus::Partial,
+            "failed" => DeliveryStatus::Failed,
+            "skipped" => DeliveryStatus::Skipped,
+            _ => DeliveryStatus::Pending,
+        }
+    }
+}
+
+impl HistoryStore {
+    const DEFAULT_LIMIT: u32 = 50;
+    const MAX_LIMIT: u32 = 500;
+
+    pub fn open(path: impl AsRef<Path>, retention_days: u64) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open history database: {}", e))?;
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                received_at INTEGER NOT NULL,
+                event TEXT NOT NULL,
+                server_name TEXT,
+                application_name TEXT,
+                project TEXT,
+                payload TEXT NOT NULL,
+                title TEXT NOT NULL,
+                body TEXT NOT NULL,
+                delivery_status TEXT NOT NULL,
+                deliveries TEXT NOT NULL DEFAULT '[]'
+            );
+            CREATE INDEX IF NOT EXISTS events_received_at ON events (received_at);
+            CREATE TABLE IF NOT EXISTS dead_letters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at INTEGER NOT NULL,
+                target_url TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                error TEXT,
+                replayed_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS dead_letters_target_url ON dead_letters (target_url);
+            CREATE TABLE IF NOT EXISTS device_groups (
+                name TEXT NOT NULL,
+                token TEXT NOT NULL,
+                PRIMARY KEY (name, token)
+            );
+            CREATE TABLE IF NOT EXISTS polled_deployments (
+                instance TEXT NOT NULL DEFAULT '',
+                uuid TEXT NOT NULL,
+                first_seen INTEGER NOT NULL,
+                payload TEXT NOT NULL,
+                PRIMARY KEY (instance, uuid)
+            );",
+        )
+        .map_err(|e| format!("Failed to create history tables: {}", e))?;
+
+        Ok(Self {
+            retention_days,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let data_dir = env::var("DATA_DIR")
+            .ok()
+            .map(|dir| dir.trim().to_string())
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or("data".to_string());
+        let retention_days = env::var("HISTORY_RETENTION_DAYS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(30);
+
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory {}: {}", data_dir, e))?;
+
+        Self::open(PathBuf::from(data_dir).join("history.db"), retention_days)
+    }
+
+    pub fn record_event(
+        &self,
+        payload: &serde_json::Value,
+        webhook_payload: &WebhookPayload,
+        notification: &Notification,
+    ) -> Result<i64, String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT INTO events (
+                received_at, event, server_name, application_name, project,
+                payload, title, body, delivery_status
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                unix_timestamp() as i64,
+                webhook_payload.event.as_deref().unwrap_or("unknown"),
+                webhook_payload.server_name,
+                webhook_payload.application_name,
+                webhook_payload.project,
+                payload.to_string(),
+                notification.title,
+                notification.body,
+                DeliveryStatus::Pending.as_str(),
+            ],
+        )
+        .map_err(|e| format!("Failed to record event: {}", e))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn record_deliveries(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        deliveries: &[ExpoDelivery],
+    ) -> Result<(), String> {
+        let deliveries = serde_json::to_string(deliveries)
+            .map_err(|e| format!("Failed to serialize deliveries: {}", e))?;
+
+        self.lock()?
+            .execute(
+                "UPDATE events SET delivery_status = ?1, deliveries = ?2 WHERE id = ?3",
+                params![status.as_str(), deliveries, id],
+            )
+            .map_err(|e| format!("Failed to record delivery outcome: {}", e))?;
+
+        Ok(())
+    }
+
+    pub fn ping(&self) -> Result<(), String> {
+        self.lock()?
+            .query_row("SELECT 1", [], |_| Ok(()))
+            .map_err(|e| format!("History database is unavailable: {}", e))
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<EventRecord>, String> {
+        self.lock()?
+            .query_row(
+                &format!("SELECT {} FROM events WHERE id = ?1", Self::COLUMNS),
+                params![id],
+                Self::row_to_record,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read event: {}", e))
+    }
+
+    pub fn query(&self, query: &EventQuery) -> Result<EventPage, String> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<SqlValue> = Vec::new();
+
+        if let Some(event) = &query.event {
+            conditions.push("event = ?");
+            values.push(SqlValue::Text(event.clone()));
+        }
+        if let Some(server) = &query.server {
+            conditions.push("server_name = ?");
+            values.push(SqlValue::Text(server.clone()));
+        }
+        if let Some(app) = &query.app {
+            conditions.push("application_name = ?");
+            values.push(SqlValue::Text(app.clone()));
+        }
+        if let Some(status) = &query.status {
+            conditions.push("delivery_status = ?");
+            values.push(SqlValue::Text(status.as_str().to_string()));
+        }
+        if let Some(from) = query.from {
+            conditions.push("received_at >= ?");
+            values.push(SqlValue::Integer(from));
+        }
+        if let Some(to) = query.to {
+            conditions.push("received_at <= ?");
+            values.push(SqlValue::Integer(to));
+        }
+
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let limit = query
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+
+        let conn = self.lock()?;
+
+        let total = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM events {}", filter),
+                params_from_iter(values.iter()),
+                |row| row.get::<_, i64>(0).map(|count| count as u64),
+            )
+            .map_err(|e| format!("Failed to count events: {}", e))?;
+
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM events {} ORDER BY received_at DESC, id DESC LIMIT {} OFFSET {}",
+                Self::COLUMNS,
+                filter,
+                limit,
+                offset
+            ))
+            .map_err(|e| format!("Failed to query events: {}", e))?;
+
+        let events = statement
+            .query_map(params_from_iter(values.iter()), Self::row_to_record)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read events: {}", e))?;
+
+        Ok(EventPage {
+            events,
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    pub fn record_dead_letter(
+        &self,
+        payload: &serde_json::Value,
+        outcome: &ForwardOutcome,
+    ) -> Result<i64, String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT INTO dead_letters (created_at, target_url, payload, attempts, error)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                unix_timestamp() as i64,
+                outcome.url,
+                payload.to_string(),
+                outcome.attempts,
+                outcome.error,
+            ],
+        )
+        .map_err(|e| format!("Failed to record dead letter: {}", e))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn dead_letters(&self, query: &DeadLetterQuery) -> Result<Vec<DeadLetter>, String> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<SqlValue> = Vec::new();
+
+        if let Some(target) = &query.target {
+            conditions.push("target_url = ?");
+            values.push(SqlValue::Text(target.clone()));
+        }
+        if !query.include_replayed.unwrap_or(false) {
+            conditions.push("replayed_at IS NULL");
+        }
+
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = self.lock()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM dead_letters {} ORDER BY id DESC LIMIT {}",
+                Self::DEAD_LETTER_COLUMNS,
+                filter,
+                Self::MAX_LIMIT
+            ))
+            .map_err(|e| format!("Failed to query dead letters: {}", e))?;
+
+        statement
+            .query_map(params_from_iter(values.iter()), Self::row_to_dead_letter)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read dead letters: {}", e))
+    }
+
+    pub fn get_dead_letter(&self, id: i64) -> Result<Option<DeadLetter>, String> {
+        self.lock()?
+            .query_row(
+                &format!(
+                    "SELECT {} FROM dead_letters WHERE id = ?1",
+                    Self::DEAD_LETTER_COLUMNS
+                ),
+                params![id],
+                Self::row_to_dead_letter,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read dead letter: {}", e))
+    }
+
+    /// Stores the outcome of a replay. A successful replay marks the dead letter
+    /// as replayed, a failed one keeps it queued with the new error.
+    pub fn record_replay(&self, id: i64, outcome: &ForwardOutcome) -> Result<(), String> {
+        let replayed_at = outcome.success.then(|| unix_timestamp() as i64);
+
+        self.lock()?
+            .execute(
+                "UPDATE dead_letters
+                SET attempts = attempts + ?1, error = ?2, replayed_at = ?3
+                WHERE id = ?4",
+                params![outcome.attempts, outcome.error, replayed_at, id],
+            )
+            .map_err(|e| format!("Failed to record replay: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Device group members managed through the admin API, by group name.
+    pub fn device_groups(&self) -> Result<BTreeMap<String, Vec<String>>, String> {
+        let conn = self.lock()?;
+        let mut statement = conn
+            .prepare("SELECT name, token FROM device_groups ORDER BY name, token")
+            .map_err(|e| format!("Failed to query device groups: {}", e))?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read device groups: {}", e))?;
+
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, token) in rows {
+            groups.entry(name).or_default().push(token);
+        }
+        Ok(groups)
+    }
+
+    /// Replaces the stored members of a group. No members removes the group.
+    pub fn set_device_group(&self, name: &str, members: &[String]) -> Result<(), String> {
+        let mut conn = self.lock()?;
+        let transaction = conn
+            .transaction()
+            .map_err(|e| format!("Failed to update device group: {}", e))?;
+        transaction
+            .execute("DELETE FROM device_groups WHERE name = ?1", params![name])
+            .map_err(|e| format!("Failed to update device group: {}", e))?;
+        for token in members {
+            transaction
+                .execute(
+                    "INSERT OR IGNORE INTO device_groups (name, token) VALUES (?1, ?2)",
+                    params![name, token],
+                )
+                .map_err(|e| format!("Failed to update device group: {}", e))?;
+        }
+        transaction
+            .commit()
+            .map_err(|e| format!("Failed to update device group: {}", e))
+    }
+
+    /// Deployments the poller of `instance` was tracking when it last ran.
+    pub fn polled_deployments(
+        &self,
+        instance: Option<&str>,
+    ) -> Result<Vec<PolledDeployment>, String> {
+        let conn = self.lock()?;
+        let mut statement = conn
+            .prepare(
+                "SELECT uuid, first_seen, payload FROM polled_deployments
+                 WHERE instance = ?1 ORDER BY first_seen",
+            )
+            .map_err(|e| format!("Failed to query poller deployments: {}", e))?;
+        statement
+            .query_map(params![instance.unwrap_or_default()], |row| {
+                let payload: String = row.get(2)?;
+                Ok(PolledDeployment {
+                    instance: instance.map(String::from),
+                    uuid: row.get(0)?,
+                    first_seen: row.get::<_, i64>(1)? as u64,
+                    payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                })
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("Failed to read poller deployments: {}", e))
+    }
+
+    pub fn record_polled_deployment(&self, deployment: &PolledDeployment) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO polled_deployments (instance, uuid, first_seen, payload)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                deployment.instance.as_deref().unwrap_or_default(),
+                deployment.uuid,
+                deployment.first_seen as i64,
+                deployment.payload.to_string()
+            ],
+        )
+        .map_err(|e| format!("Failed to record poller deployment: {}", e))?;
+
+        Ok(())
+    }
+
+    pub fn remove_polled_deployment(
+        &self,
+        instance: Option<&str>,
+        uuid: &str,
+    ) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "DELETE FROM polled_deployments WHERE instance = ?1 AND uuid = ?2",
+            params![instance.unwrap_or_default(), uuid],
+        )
+        .map_err(|e| format!("Failed to remove poller deployment: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Removes events and dead letters older than the retention window. A
+    /// retention of `0` keeps everything.
+    pub fn prune(&self) -> Result<usize, String> {
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+
+        let cutoff = unix_timestamp().saturating_sub(self.retention_days * 86400) as i64;
+        let conn = self.lock()?;
+        let events = conn
+            .execute("DELETE FROM events WHERE received_at < ?1", params![cutoff])
+            .map_err(|e| format!("Failed to prune history: {}", e))?;
+        let dead_letters = conn
+            .execute(
+                "DELETE FROM dead_letters WHERE created_at < ?1",
+                params![cutoff],
+            )
+            .map_err(|e| format!("Failed to prune dead letters: {}", e))?;
+
+        Ok(events + dead_letters)
+    }
+
+    pub fn start_pruning(history: Arc<HistoryStore>) {
+        tokio::spawn(async move {
+            loop {
+                match history.prune() {
+                    Ok(0) => (),
+                    Ok(removed) => info!("Pruned {} records from history", removed),
+                    Err(error) => error!("{}", error),
+                }
+
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+            }
+        });
+    }
+
+    const COLUMNS: &str = "id, received_at, event, server_name, application_name, project, \
+        payload, title, body, delivery_status, deliveries";
+
+    fn row_to_record(row: &rusqlite::Row) -> rusqlite::Result<EventRecord> {
+        let payload: String = row.get(6)?;
+        let delivery_status: String = row.get(9)?;
+        let deliveries: String = row.get(10)?;
+
+        Ok(EventRecord {
+            id: row.get(0)?,
+            received_at: row.get(1)?,
+            event: row.get(2)?,
+            server_name: row.get(3)?,
+            application_name: row.get(4)?,
+            project: row.get(5)?,
+            payload: serde_json::from_str(&payload).unwrap_or_default(),
+            notification: Notification {
+                title: row.get(7)?,
+                body: row.get(8)?,
+            },
+            delivery_status: DeliveryStatus::parse(&delivery_status),
+            deliveries: serde_json::from_str(&deliveries).unwrap_or_default(),
+        })
+    }
+
+    const DEAD_LETTER_COLUMNS: &str =
+        "id, created_at, target_url, payload, attempts, error, replayed_at";
+
+    fn row_to_dead_letter(row: &rusqlite::Row) -> rusqlite::Result<DeadLetter> {
+        let payload: String = row.get(3)?;
+
+        Ok(DeadLetter {
+            id: row.get(0)?,
+            created_at: row.get(1)?,
+            target_url: row.get(2)?,
+            payload: serde_json::from_str(&payload).unwrap_or_default(),
+            attempts: row.get(4)?,
+            error: row.get(5)?,
+            replayed_at: row.get(6)?,
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn
+            .lock()
+            .map_err(|_| "History database lock is poisoned.".to_string())
+    }
+}
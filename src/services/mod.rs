@@ -1,53 +1,268 @@
+pub mod admin;
 pub mod expo;
+pub mod dashboard;
 pub mod deployment_poller;
+pub mod discord;
+pub mod endpoints;
+pub mod gotify;
+pub mod groups;
+pub mod health;
+pub mod history;
+pub mod ntfy;
+pub mod output;
 pub mod repeater;
+pub mod retry;
+pub mod sink;
+pub mod slack;
+pub mod teams;
+pub mod telegram;
 pub mod updater;
 
-use axum::{Json, extract::State, response::IntoResponse};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use futures_util::future::join_all;
 
 use reqwest::StatusCode;
+use serde::Deserialize;
 use serde_json::Value;
 use std::sync::Arc;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+use uuid::Uuid;
 
 use crate::{
-    WebhookPayload,
+    Notification, WebhookPayload,
     event_parser::{self},
-    services::expo::ExpoNotification,
+    metrics,
+    services::{endpoints::WebhookEndpoint, history::EventQuery, sink::SinkReport},
     state::AppState,
 };
 
+/// Fields the relay sets itself: the endpoint a webhook was received by, and
+/// the instance and duration of the deployments it polls.
+const RELAY_FIELDS: &[&str] = &["endpoint", "instance", "duration_secs"];
+
 pub async fn handle_webhook(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<Value>,
+    Json(mut payload): Json<Value>,
 ) -> impl IntoResponse {
-    let Ok(webhook_payload) = WebhookPayload::from_value(payload.clone()) else {
-        return (StatusCode::BAD_REQUEST, "Invalid payload").into_response();
+    strip_relay_fields(&mut payload);
+    accept_webhook(state, None, payload)
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct EndpointQuery {
+    pub secret: Option<String>,
+}
+
+/// Receives webhooks for a named endpoint at `/hooks/{name}`.
+pub async fn handle_endpoint_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(query): Query<EndpointQuery>,
+    headers: HeaderMap,
+    Json(mut payload): Json<Value>,
+) -> Response {
+    let Some(endpoint) = state.endpoint(&name) else {
+        return (StatusCode::NOT_FOUND, "Unknown endpoint").into_response();
     };
+    if !endpoint.is_authorized(&headers, query.secret.as_deref()) {
+        warn!(endpoint = %name, "Rejected webhook with invalid secret");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    }
+
+    strip_relay_fields(&mut payload);
+    accept_webhook(state, Some(endpoint), payload)
+}
 
-    println!(
-        "Received event: {}",
-        webhook_payload.event.as_deref().unwrap_or("unknown event")
+/// Drops the [`RELAY_FIELDS`] from a received webhook, so they cannot be
+/// spoofed by the sender.
+fn strip_relay_fields(payload: &mut Value) {
+    if let Some(fields) = payload.as_object_mut() {
+        for field in RELAY_FIELDS {
+            fields.remove(*field);
+        }
+    }
+}
+
+/// Records a webhook and delivers it in the background. Webhooks received by
+/// a named endpoint are tagged with its name and use its settings. The
+/// deployment poller calls this directly with the events it detects.
+pub fn accept_webhook(
+    state: Arc<AppState>,
+    endpoint: Option<Arc<WebhookEndpoint>>,
+    mut payload: Value,
+) -> Response {
+    let correlation_id = Uuid::new_v4().to_string();
+    let span = info_span!(
+        "webhook",
+        correlation_id = %correlation_id,
+        endpoint = tracing::field::Empty
     );
+    if let Some(endpoint) = &endpoint {
+        span.record("endpoint", endpoint.name.as_str());
+        endpoint.tag(&mut payload);
+    }
 
-    let notification = event_parser::parse_event(&webhook_payload);
+    let Ok(webhook_payload) = WebhookPayload::from_value(payload.clone()) else {
+        span.in_scope(|| warn!("Rejected webhook with invalid payload"));
+        return (StatusCode::BAD_REQUEST, "Invalid payload").into_response();
+    };
 
-    tokio::spawn(async move {
-        let result = state.repeater.forward(&payload).await;
+    let notification = span.in_scope(|| {
+        info!(
+            event = webhook_payload.event.as_deref().unwrap_or("unknown event"),
+            "Received event"
+        );
+        metrics::WEBHOOKS_RECEIVED
+            .with_label_values(&[metrics::event_label(webhook_payload.event.as_deref())])
+            .inc();
+        if event_parser::is_fallback(&webhook_payload) {
+            metrics::PARSE_FALLBACKS
+                .with_label_values(&[metrics::event_label(webhook_payload.event.as_deref())])
+                .inc();
+        }
 
-        match result {
-            Ok(()) => println!("Forwarded webhook to repeaters"),
-            Err(e) => eprintln!("Failed to forward webhook to repeaters: {}", e),
+        let notification = event_parser::parse_event(&webhook_payload);
+        match &endpoint {
+            Some(endpoint) => endpoint.render(&payload, notification),
+            None => notification,
         }
+    });
+
+    metrics::OUTBOX_DEPTH.inc();
+    tokio::spawn(deliver(state, endpoint, payload, webhook_payload, notification).instrument(span));
 
-        state
-            .expo
-            .send_notification(ExpoNotification {
-                title: notification.title,
-                body: notification.body,
-                data: payload,
+    (
+        StatusCode::ACCEPTED,
+        [("X-Correlation-Id", correlation_id)],
+        "OK",
+    )
+        .into_response()
+}
+
+/// Stores the event in the history. SQLite writes block, so they run off
+/// the async workers.
+async fn record_event(
+    state: &AppState,
+    payload: &Value,
+    webhook_payload: WebhookPayload,
+    notification: &Notification,
+) -> Option<i64> {
+    let history = state.history.clone();
+    let (payload, notification) = (payload.clone(), notification.clone());
+    tokio::task::spawn_blocking(move || {
+        history.record_event(&payload, &webhook_payload, &notification)
+    })
+    .await
+    .map_err(|e| format!("Failed to record event: {}", e))
+    .and_then(|result| result)
+    .inspect_err(|error| error!("{}", error))
+    .ok()
+}
+
+async fn deliver(
+    state: Arc<AppState>,
+    endpoint: Option<Arc<WebhookEndpoint>>,
+    payload: Value,
+    webhook_payload: WebhookPayload,
+    notification: Notification,
+) {
+    let event_id = record_event(&state, &payload, webhook_payload, &notification).await;
+    let route = state.routes.route(&payload, &notification);
+    let selected = state
+        .sinks
+        .iter()
+        .filter_map(|sink| {
+            let targets = route.targets(sink.name())?;
+            // Targets a route names, e.g. `repeater:<name>` or `expo:<group>`,
+            // come from the global configuration, so they are not looked up
+            // in the endpoint's own repeaters or devices.
+            Some(match &endpoint {
+                Some(endpoint) if targets.is_empty() => {
+                    (endpoint.sink(sink), endpoint.targets(sink.name()))
+                }
+                _ => (sink.clone(), targets),
             })
-            .await;
-    });
+        })
+        .collect::<Vec<_>>();
+    debug!(
+        rules = ?route.rules,
+        default = route.default,
+        destinations = ?route.destinations,
+        "Routed event"
+    );
+
+    // Sinks are independent, so a slow repeater or chat service must not
+    // delay the push notification.
+    let reports = join_all(selected.iter().map(|(sink, targets)| {
+        if targets.is_empty() {
+            sink.deliver(&payload, &notification)
+        } else {
+            sink.deliver_to(&payload, &notification, targets)
+        }
+    }))
+    .await;
+    let report = reports
+        .into_iter()
+        .fold(SinkReport::default(), |mut report, other| {
+            report.merge(other);
+            report
+        });
 
-    (StatusCode::ACCEPTED, "OK").into_response()
+    for outcome in report.failed_forwards.iter() {
+        if let Err(error) = state.history.record_dead_letter(&payload, outcome) {
+            error!("{}", error);
+        }
+    }
+    info!(
+        sinks = selected.len(),
+        failed_forwards = report.failed_forwards.len(),
+        errors = report.errors.len(),
+        "Delivered notification"
+    );
+
+    if let Some(event_id) = event_id
+        && let Err(error) = state
+            .history
+            .record_deliveries(event_id, report.status(), &report.deliveries)
+    {
+        error!("{}", error);
+    }
+    metrics::OUTBOX_DEPTH.dec();
+}
+
+pub async fn list_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventQuery>,
+) -> impl IntoResponse {
+    match state.history.query(&query) {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(error) => {
+            error!("{}", error);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query events").into_response()
+        }
+    }
+}
+
+pub async fn show_metrics() -> impl IntoResponse {
+    match metrics::render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [("Content-Type", "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        Err(error) => {
+            error!("{}", error);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to render metrics",
+            )
+                .into_response()
+        }
+    }
 }
@@ -0,0 +1,138 @@
+use std::env;
+
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventFamily, EventSeverity},
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+};
+
+/// Publishes notifications to a topic on an ntfy server.
+pub struct NtfyService {
+    pub server_url: String,
+    pub topic: String,
+    pub access_token: Option<String>,
+    pub filter: EventFilter,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+impl NtfyService {
+    /// ntfy shows at most three action buttons.
+    const MAX_ACTIONS: usize = 3;
+
+    pub fn new(server_url: String, topic: String, client: reqwest::Client) -> Self {
+        Self {
+            server_url,
+            topic,
+            access_token: None,
+            filter: EventFilter::default(),
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `NTFY_TOPIC` and the optional `NTFY_URL`, `NTFY_ACCESS_TOKEN` and
+    /// `NTFY_FILTER`. Returns `None` when no topic is configured.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(topic) = env::var("NTFY_TOPIC")
+            .ok()
+            .map(|topic| topic.trim().to_string())
+            .filter(|topic| !topic.is_empty())
+        else {
+            return Ok(None);
+        };
+        let server_url = env::var("NTFY_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .unwrap_or("https://ntfy.sh".to_string());
+
+        Ok(Some(Self {
+            access_token: env::var("NTFY_ACCESS_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+            filter: EventFilter::from_env("NTFY_FILTER")?,
+            ..Self::new(server_url, topic, client)
+        }))
+    }
+
+    /// Builds the JSON publish request. Severity maps to the ntfy priority
+    /// (`3` default, `4` high, `5` urgent), the event family and name to tags,
+    /// and links in the payload to a click URL and view actions.
+    pub fn message(&self, payload: &WebhookPayload, notification: &Notification) -> Value {
+        let event = payload.event.as_deref().unwrap_or("");
+        let priority = match event_parser::event_severity(event) {
+            EventSeverity::Info => 3,
+            EventSeverity::Warning => 4,
+            EventSeverity::Critical => 5,
+        };
+        let family = match event_parser::event_family(event) {
+            EventFamily::Success => "white_check_mark",
+            EventFamily::Failure => "rotating_light",
+            EventFamily::Info => "information_source",
+        };
+        let mut tags = vec![family.to_string()];
+        if !event.is_empty() {
+            tags.push(event.to_string());
+        }
+
+        let links = event_parser::links(payload);
+        let actions = links
+            .iter()
+            .take(Self::MAX_ACTIONS)
+            .map(|link| json!({ "action": "view", "label": link.label, "url": link.url }))
+            .collect::<Vec<_>>();
+
+        let mut message = json!({
+            "topic": self.topic,
+            "title": notification.title,
+            "message": notification.body,
+            "priority": priority,
+            "tags": tags,
+        });
+        if let Some(link) = links.first() {
+            message["click"] = json!(link.url);
+            message["actions"] = json!(actions);
+        }
+        message
+    }
+
+    /// Publishes the notification unless the filter rejects the webhook, in
+    /// which case `Ok(false)` is returned.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        if !self.filter.matches(payload) {
+            debug!("Webhook filtered out for ntfy");
+            return Ok(false);
+        }
+
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let message = self.message(&webhook_payload, notification).to_string();
+        // JSON messages are published to the server root, not the topic URL.
+        let url = self.server_url.trim_end_matches('/');
+
+        output::send("ntfy", &self.retry_policy, || {
+            let request = self
+                .client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .body(message.clone());
+            match &self.access_token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+        })
+        .await?;
+
+        Ok(true)
+    }
+}
+
+chat_sink!(NtfyService, "ntfy");
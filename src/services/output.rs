@@ -0,0 +1,37 @@
+use reqwest::RequestBuilder;
+use tracing::{debug, warn};
+
+use crate::{
+    metrics,
+    services::retry::{RetryPolicy, SendOutcome},
+};
+
+/// Sends a chat output request under `retry_policy`, then records and logs
+/// the outcome. Any non-2xx response is an error that includes the body.
+pub async fn send(
+    output: &'static str,
+    retry_policy: &RetryPolicy,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<(), String> {
+    let SendOutcome { result, attempts } = retry_policy.send(build).await;
+
+    let result = match result {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            Err(format!("{} responded with {}: {}", output, status, body))
+        }
+        Err(error) => Err(error),
+    };
+
+    metrics::OUTPUT_SENDS
+        .with_label_values(&[output, if result.is_ok() { "success" } else { "error" }])
+        .inc();
+    match &result {
+        Ok(()) => debug!(output, attempts, "Sent notification"),
+        Err(e) => warn!(output, attempts, "Failed to send notification: {}", e),
+    }
+
+    result
+}
@@ -1,40 +1,580 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{BoxFuture, join_all};
+use reqwest::{Method, RequestBuilder, header::HeaderName};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{
+    Notification,
+    filter::EventFilter,
+    metrics,
+    services::{
+        retry::{RetryPolicy, SendOutcome},
+        sink::{NotificationSink, SinkReport},
+    },
+    signing,
+    template::TemplateContext,
+    utils::unix_timestamp,
+};
+
 pub struct WebhookRepeaterService {
-    pub urls: Vec<String>,
+    pub targets: Vec<RepeaterTarget>,
     pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+    pub timeout: Duration,
+    /// Clients for targets with their own TLS options, keyed by URL.
+    tls_clients: HashMap<String, reqwest::Client>,
+    statuses: Mutex<HashMap<String, RepeaterStatus>>,
+}
+
+/// A URL the relay forwards webhooks to, with the request settings it needs.
+/// Requests are signed as described in [`crate::signing`] when
+/// `signing_secret` is set.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RepeaterTarget {
+    /// Lets routing rules select this target, e.g. `repeater:audit`.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub url: String,
+    #[serde(default = "RepeaterTarget::default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth: Option<RepeaterAuth>,
+    /// Overrides the service wide timeout for this target.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub tls: TlsOptions,
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+    /// Limits which webhooks are forwarded to this target.
+    #[serde(default)]
+    pub filter: EventFilter,
+    /// JSON body to send instead of the raw webhook, see
+    /// [`TemplateContext::render`].
+    #[serde(default)]
+    pub template: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RepeaterAuth {
+    Bearer {
+        token: String,
+    },
+    Basic {
+        username: String,
+        password: String,
+    },
+    ApiKey {
+        #[serde(default = "RepeaterAuth::default_api_key_header")]
+        header: String,
+        key: String,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct TlsOptions {
+    /// Skips certificate validation, e.g. for self-signed internal targets.
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    /// Path to an additional PEM encoded root certificate to trust.
+    #[serde(default)]
+    pub ca_cert: Option<String>,
+}
+
+impl RepeaterTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            name: None,
+            url: url.into(),
+            method: Self::default_method(),
+            headers: BTreeMap::new(),
+            auth: None,
+            timeout_secs: None,
+            tls: TlsOptions::default(),
+            signing_secret: None,
+            filter: EventFilter::default(),
+            template: None,
+        }
+    }
+
+    fn default_method() -> String {
+        "POST".to_string()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if Method::from_bytes(self.method.to_uppercase().as_bytes()).is_err() {
+            return Err(format!("{}: invalid method {}", self.url, self.method));
+        }
+
+        let mut names = self.headers.keys().map(String::as_str).collect::<Vec<_>>();
+        if let Some(RepeaterAuth::ApiKey { header, .. }) = &self.auth {
+            names.push(header);
+        }
+        for name in names {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("{}: invalid header name {}", self.url, name));
+            }
+        }
+        if !self.tls.is_default()
+            && let Err(e) = self.tls.build_client()
+        {
+            return Err(format!("{}: {}", self.url, e));
+        }
+
+        Ok(())
+    }
+
+    /// The request body: the rendered template, or the raw webhook.
+    fn body(&self, raw: &str, context: &TemplateContext) -> String {
+        match &self.template {
+            Some(template) => context.render(template).to_string(),
+            None => raw.to_string(),
+        }
+    }
+
+    fn method(&self) -> Method {
+        Method::from_bytes(self.method.to_uppercase().as_bytes()).unwrap_or(Method::POST)
+    }
+
+    fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = self.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        });
+
+        match &self.auth {
+            Some(RepeaterAuth::Bearer { token }) => request.bearer_auth(token),
+            Some(RepeaterAuth::Basic { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            Some(RepeaterAuth::ApiKey { header, key }) => request.header(header, key),
+            None => request,
+        }
+    }
+}
+
+impl RepeaterAuth {
+    fn default_api_key_header() -> String {
+        "X-Api-Key".to_string()
+    }
+}
+
+impl TlsOptions {
+    fn is_default(&self) -> bool {
+        !self.accept_invalid_certs && self.ca_cert.is_none()
+    }
+
+    fn build_client(&self) -> Result<reqwest::Client, String> {
+        let mut builder =
+            reqwest::Client::builder().danger_accept_invalid_certs(self.accept_invalid_certs);
+
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid certificate {}: {}", path, e))?;
+            if certificates.is_empty() {
+                return Err(format!("No certificate found in {}", path));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ForwardOutcome {
+    pub url: String,
+    pub success: bool,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RepeaterStatus {
+    pub url: String,
+    pub last_attempt_at: Option<u64>,
+    pub last_success_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_duration_ms: Option<u64>,
+}
+
+impl RepeaterStatus {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            last_attempt_at: None,
+            last_success_at: None,
+            last_error: None,
+            last_duration_ms: None,
+        }
+    }
 }
 
 impl WebhookRepeaterService {
     const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
     const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-    pub async fn forward(&self, payload: &serde_json::Value) -> Result<(), &str> {
-        let Ok(body) = serde_json::to_string(payload) else {
+    pub fn new(targets: Vec<RepeaterTarget>, client: reqwest::Client) -> Self {
+        let tls_clients = targets
+            .iter()
+            .filter(|target| !target.tls.is_default())
+            .filter_map(|target| match target.tls.build_client() {
+                Ok(client) => Some((target.url.clone(), client)),
+                Err(e) => {
+                    error!(url = %target.url, "Failed to configure repeater TLS: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            targets,
+            client,
+            retry_policy: RetryPolicy::default(),
+            timeout: Duration::from_secs(10),
+            tls_clients,
+            statuses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reads plain POST targets from `WEBHOOK_RELAY_URLS` and, optionally,
+    /// targets with their own settings from the `WEBHOOK_RELAY_TARGETS` JSON
+    /// array.
+    pub fn from_env(client: reqwest::Client) -> Result<Self, String> {
+        let mut targets = env::var("WEBHOOK_RELAY_URLS")
+            .unwrap_or("".to_string())
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .map(RepeaterTarget::new)
+            .collect::<Vec<RepeaterTarget>>();
+        if let Ok(json) = env::var("WEBHOOK_RELAY_TARGETS") {
+            let parsed = Self::parse_targets(&json)
+                .map_err(|e| format!("Invalid WEBHOOK_RELAY_TARGETS: {}", e))?;
+            targets.extend(parsed);
+        }
+        let max_attempts = env::var("WEBHOOK_RELAY_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+            .unwrap_or(3);
+        let retry_delay_ms = env::var("WEBHOOK_RELAY_RETRY_DELAY_MS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(1000);
+        let timeout_secs = env::var("WEBHOOK_RELAY_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(10);
+
+        Ok(Self {
+            retry_policy: RetryPolicy::new(max_attempts, Duration::from_millis(retry_delay_ms)),
+            timeout: Duration::from_secs(timeout_secs),
+            ..Self::new(targets, client)
+        })
+    }
+
+    pub fn parse_targets(json: &str) -> Result<Vec<RepeaterTarget>, String> {
+        if json.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let targets = serde_json::from_str::<Vec<RepeaterTarget>>(json)
+            .map_err(|e| format!("Invalid repeater targets: {}", e))?;
+        for target in targets.iter() {
+            target.validate()?;
+        }
+
+        Ok(targets)
+    }
+
+    /// Latest forwarding outcome for every configured URL.
+    pub fn statuses(&self) -> Vec<RepeaterStatus> {
+        self.labelled_statuses()
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect()
+    }
+
+    /// Like [`Self::statuses`], paired with the [`Self::label`] of each
+    /// target for reports that must not show URLs.
+    pub fn labelled_statuses(&self) -> Vec<(String, RepeaterStatus)> {
+        let statuses = self.statuses.lock().map(|s| s.clone()).unwrap_or_default();
+
+        self.targets
+            .iter()
+            .map(|target| {
+                let status = statuses
+                    .get(&target.url)
+                    .cloned()
+                    .unwrap_or_else(|| RepeaterStatus::new(&target.url));
+                (self.label(target), status)
+            })
+            .collect()
+    }
+
+    /// Forwards the payload concurrently to every configured target whose
+    /// filter accepts it, so a slow target does not hold up the others. Each
+    /// request is bounded by `timeout` and failed deliveries are retried
+    /// according to `retry_policy`. Filtered out targets have no outcome.
+    /// Targets with a template receive it rendered with `notification`.
+    pub async fn forward(
+        &self,
+        payload: &serde_json::Value,
+        notification: &Notification,
+    ) -> Result<Vec<ForwardOutcome>, &str> {
+        self.forward_matching(payload, notification, |_| true).await
+    }
+
+    /// Like [`Self::forward`], limited to the targets whose name or URL is
+    /// listed in `names`. Names that match no target are logged.
+    pub async fn forward_named(
+        &self,
+        payload: &serde_json::Value,
+        notification: &Notification,
+        names: &[String],
+    ) -> Result<Vec<ForwardOutcome>, &str> {
+        let is_named = |target: &RepeaterTarget, name: &String| {
+            target.name.as_ref() == Some(name) || target.url == *name
+        };
+        let unknown = names
+            .iter()
+            .filter(|name| !self.targets.iter().any(|target| is_named(target, name)))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            warn!(targets = ?unknown, "Unknown repeater targets");
+        }
+
+        self.forward_matching(payload, notification, |target| {
+            names.iter().any(|name| is_named(target, name))
+        })
+        .await
+    }
+
+    async fn forward_matching(
+        &self,
+        payload: &serde_json::Value,
+        notification: &Notification,
+        selected: impl Fn(&RepeaterTarget) -> bool,
+    ) -> Result<Vec<ForwardOutcome>, &str> {
+        let Ok(raw) = serde_json::to_string(payload) else {
             return Err("Failed to serialize payload.");
         };
+        let context = TemplateContext {
+            notification,
+            payload,
+        };
+
+        let targets = self
+            .targets
+            .iter()
+            .filter(|target| selected(target))
+            .filter(|target| {
+                let accepted = target.filter.matches(payload);
+                if !accepted {
+                    debug!(target = %self.label(target), "Webhook filtered out for repeater");
+                }
+                accepted
+            });
+
+        Ok(join_all(targets.map(|target| self.send(target, target.body(&raw, &context)))).await)
+    }
+
+    /// Forwards the payload to a single URL, e.g. to replay a dead letter.
+    /// The settings of the configured target with that URL are used, if any.
+    pub async fn forward_to(
+        &self,
+        url: &str,
+        payload: &serde_json::Value,
+        notification: &Notification,
+    ) -> Result<ForwardOutcome, &str> {
+        let Ok(raw) = serde_json::to_string(payload) else {
+            return Err("Failed to serialize payload.");
+        };
+        let context = TemplateContext {
+            notification,
+            payload,
+        };
+
+        let target = self
+            .targets
+            .iter()
+            .find(|target| target.url == url)
+            .cloned()
+            .unwrap_or_else(|| RepeaterTarget::new(url));
+
+        Ok(self.send(&target, target.body(&raw, &context)).await)
+    }
 
-        for url in self.urls.iter() {
-            let result = self
-                .client
-                .post(url)
-                .body(body.clone())
-                .header("Content-Type", "application/json")
-                .header(
-                    "User-Agent",
-                    format!(
-                        "{} v{}",
-                        WebhookRepeaterService::PACKAGE_NAME,
-                        WebhookRepeaterService::VERSION
-                    ),
-                )
-                .send()
-                .await;
-
-            match result {
-                Err(e) => eprintln!("Failed to forward webhook to repeater: {}", e),
-                _ => (),
+    /// Identifies a target in metrics, logs and the readiness report by its
+    /// name, or its position in the configured targets. URLs are not used,
+    /// since they can carry tokens and any URL can be replayed to.
+    pub fn label(&self, target: &RepeaterTarget) -> String {
+        if let Some(name) = &target.name {
+            return name.clone();
+        }
+        match self.targets.iter().position(|t| t.url == target.url) {
+            Some(index) => index.to_string(),
+            None => "other".to_string(),
+        }
+    }
+
+    async fn send(&self, target: &RepeaterTarget, body: String) -> ForwardOutcome {
+        let url = target.url.as_str();
+        let client = self.tls_clients.get(url).unwrap_or(&self.client);
+        let timeout = target
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(self.timeout);
+        let started_at = Instant::now();
+        let SendOutcome { result, attempts } = self
+            .retry_policy
+            .send(|| {
+                let request = client
+                    .request(target.method(), url)
+                    .timeout(timeout)
+                    .body(body.clone())
+                    .header("Content-Type", "application/json")
+                    .header(
+                        "User-Agent",
+                        format!(
+                            "{} v{}",
+                            WebhookRepeaterService::PACKAGE_NAME,
+                            WebhookRepeaterService::VERSION
+                        ),
+                    );
+                let request = target.apply(request);
+
+                // Every attempt is signed with a fresh timestamp, so retries
+                // are not rejected by the receiver's replay tolerance.
+                match &target.signing_secret {
+                    Some(secret) => {
+                        let timestamp = unix_timestamp();
+                        request
+                            .header(signing::TIMESTAMP_HEADER, timestamp.to_string())
+                            .header(
+                                signing::SIGNATURE_HEADER,
+                                signing::sign(secret, timestamp, body.as_bytes()),
+                            )
+                    }
+                    None => request,
+                }
+            })
+            .await;
+
+        let error = match result {
+            Ok(response) if response.status().is_success() => None,
+            Ok(response) => Some(format!("Repeater responded with {}", response.status())),
+            Err(error) => Some(error),
+        };
+
+        let duration = started_at.elapsed();
+        let duration_ms = duration.as_millis() as u64;
+        let outcome = if error.is_none() { "success" } else { "error" };
+
+        let label = self.label(target);
+        match &error {
+            None => debug!(target = %label, attempts, duration_ms, "Forwarded webhook to repeater"),
+            Some(e) => warn!(
+                target = %label,
+                attempts,
+                duration_ms,
+                "Failed to forward webhook to repeater: {}",
+                e
+            ),
+        }
+        metrics::REPEATER_FORWARDS
+            .with_label_values(&[label.as_str(), outcome])
+            .inc();
+        metrics::REPEATER_FORWARD_DURATION
+            .with_label_values(&[label.as_str(), outcome])
+            .observe(duration.as_secs_f64());
+        self.record_status(url, error.clone(), duration_ms);
+
+        ForwardOutcome {
+            url: url.to_string(),
+            success: error.is_none(),
+            attempts,
+            error,
+            duration_ms,
+        }
+    }
+
+    fn record_status(&self, url: &str, error: Option<String>, duration_ms: u64) {
+        let Ok(mut statuses) = self.statuses.lock() else {
+            return;
+        };
+
+        let now = Some(unix_timestamp());
+        let status = statuses
+            .entry(url.to_string())
+            .or_insert_with(|| RepeaterStatus::new(url));
+
+        status.last_attempt_at = now;
+        status.last_duration_ms = Some(duration_ms);
+        match error {
+            Some(error) => status.last_error = Some(error),
+            None => {
+                status.last_success_at = now;
+                status.last_error = None;
             }
         }
+    }
+}
 
-        Ok(())
+impl NotificationSink for WebhookRepeaterService {
+    fn name(&self) -> &'static str {
+        "repeater"
+    }
+
+    fn deliver<'a>(
+        &'a self,
+        payload: &'a serde_json::Value,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, SinkReport> {
+        Box::pin(async move { report(self.forward(payload, notification).await) })
+    }
+
+    fn deliver_to<'a>(
+        &'a self,
+        payload: &'a serde_json::Value,
+        notification: &'a Notification,
+        targets: &'a [String],
+    ) -> BoxFuture<'a, SinkReport> {
+        Box::pin(async move { report(self.forward_named(payload, notification, targets).await) })
+    }
+}
+
+fn report(result: Result<Vec<ForwardOutcome>, &str>) -> SinkReport {
+    match result {
+        Ok(outcomes) => {
+            let total = outcomes.len();
+            let failed_forwards: Vec<_> = outcomes
+                .into_iter()
+                .filter(|outcome| !outcome.success)
+                .collect();
+            SinkReport {
+                sent: total - failed_forwards.len(),
+                failed: failed_forwards.len(),
+                failed_forwards,
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            error!("Failed to forward webhook to repeaters: {}", e);
+            SinkReport::from_result(Err(e.to_string()))
+        }
     }
 }
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use reqwest::{RequestBuilder, Response, StatusCode, header::RETRY_AFTER};
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+/// Result of sending a request under a [`RetryPolicy`]. `result` holds the
+/// last response, or the last error when no response was received at all.
+pub struct SendOutcome {
+    pub result: Result<Response, String>,
+    pub attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1000),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before retrying after the given (1-based) failed attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// Sends the request built by `build` until it succeeds, fails with a
+    /// non-retryable status, or runs out of attempts. Transport errors, `408`,
+    /// `429` and `5xx` responses are retried; a `Retry-After` header in seconds
+    /// takes precedence over the exponential delay, followed by a `retry_after`
+    /// field in a JSON `429` body as sent by Discord and, under `parameters`,
+    /// by Telegram.
+    pub async fn send(&self, build: impl Fn() -> RequestBuilder) -> SendOutcome {
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            let (result, retry_after) = match build().send().await {
+                Ok(response) if is_retryable(response.status()) => {
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.trim().parse::<f64>().ok())
+                        .and_then(retry_after_delay);
+                    (Ok(response), retry_after)
+                }
+                Ok(response) => {
+                    return SendOutcome {
+                        result: Ok(response),
+                        attempts: attempt,
+                    };
+                }
+                // The URL can carry secrets such as bot tokens or webhook
+                // keys, so keep it out of errors that end up in logs.
+                Err(error) => (Err(error.without_url().to_string()), None),
+            };
+
+            if attempt >= self.max_attempts {
+                return SendOutcome {
+                    result,
+                    attempts: attempt,
+                };
+            }
+
+            let retry_after = match (retry_after, result) {
+                (None, Ok(response)) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    body_retry_after(response).await
+                }
+                (retry_after, _) => retry_after,
+            };
+            let delay = retry_after
+                .map(|delay| delay.min(self.max_delay))
+                .unwrap_or_else(|| self.delay_for(attempt));
+            debug!(
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "Retrying request"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+async fn body_retry_after(response: Response) -> Option<Duration> {
+    let body = response.text().await.ok()?;
+    let body = serde_json::from_str::<serde_json::Value>(&body).ok()?;
+    body.get("retry_after")
+        .or_else(|| body.pointer("/parameters/retry_after"))
+        .and_then(serde_json::Value::as_f64)
+        .and_then(retry_after_delay)
+}
+
+/// Converts a server supplied delay in seconds, rejecting negative, NaN and
+/// out of range values instead of panicking on them. Callers cap the result
+/// at `max_delay`.
+fn retry_after_delay(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+
+use crate::{
+    Notification,
+    services::{
+        discord::DiscordService, expo::ExpoDelivery, gotify::GotifyService,
+        history::DeliveryStatus, ntfy::NtfyService, repeater::ForwardOutcome, slack::SlackService,
+        teams::TeamsService, telegram::TelegramService,
+    },
+};
+
+/// An output that receives every webhook once it has been rendered.
+///
+/// `deliver` returns a boxed future so sinks can be stored as trait objects
+/// in [`crate::state::AppState`]. Sinks handle retries themselves and report
+/// what happened instead of failing.
+pub trait NotificationSink: Send + Sync {
+    /// Short name used in logs, e.g. `expo` or `slack`.
+    fn name(&self) -> &'static str;
+
+    fn deliver<'a>(
+        &'a self,
+        payload: &'a Value,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, SinkReport>;
+
+    /// Delivers to the named targets of this sink only, when a route selects
+    /// e.g. a single repeater target. Sinks without targets deliver as usual.
+    fn deliver_to<'a>(
+        &'a self,
+        payload: &'a Value,
+        notification: &'a Notification,
+        targets: &'a [String],
+    ) -> BoxFuture<'a, SinkReport> {
+        let _ = targets;
+        self.deliver(payload, notification)
+    }
+}
+
+/// The result of delivering to a sink, merged across sinks to update the
+/// event history.
+#[derive(Debug, Default)]
+pub struct SinkReport {
+    /// Messages that reached their destination, e.g. one per push token.
+    pub sent: usize,
+    /// Messages that could not be delivered.
+    pub failed: usize,
+    /// Push deliveries, stored with the event.
+    pub deliveries: Vec<ExpoDelivery>,
+    /// Forwards that exhausted their retries and are kept as dead letters.
+    pub failed_forwards: Vec<ForwardOutcome>,
+    /// Failures the sink has already logged.
+    pub errors: Vec<String>,
+}
+
+impl SinkReport {
+    /// Chat outputs return `Ok(false)` when their filter rejected the event,
+    /// which counts as neither sent nor failed.
+    pub fn from_result(result: Result<bool, String>) -> Self {
+        Self {
+            sent: usize::from(result == Ok(true)),
+            failed: usize::from(result.is_err()),
+            errors: result.err().into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_deliveries(deliveries: Vec<ExpoDelivery>) -> Self {
+        let sent = deliveries
+            .iter()
+            .filter(|delivery| delivery.success)
+            .count();
+        Self {
+            sent,
+            failed: deliveries.len() - sent,
+            deliveries,
+            ..Default::default()
+        }
+    }
+
+    /// The event's delivery status across every sink it was routed to.
+    pub fn status(&self) -> DeliveryStatus {
+        match (self.sent, self.failed) {
+            (0, 0) => DeliveryStatus::Skipped,
+            (_, 0) => DeliveryStatus::Delivered,
+            (0, _) => DeliveryStatus::Failed,
+            _ => DeliveryStatus::Partial,
+        }
+    }
+
+    pub fn merge(&mut self, other: SinkReport) {
+        self.sent += other.sent;
+        self.failed += other.failed;
+        self.deliveries.extend(other.deliveries);
+        self.failed_forwards.extend(other.failed_forwards);
+        self.errors.extend(other.errors);
+    }
+}
+
+/// Implements [`NotificationSink`] for a chat output, whose
+/// `send_notification(payload, notification)` returns `Result<bool, String>`.
+macro_rules! chat_sink {
+    ($service:ty, $name:literal) => {
+        impl $crate::services::sink::NotificationSink for $service {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn deliver<'a>(
+                &'a self,
+                payload: &'a serde_json::Value,
+                notification: &'a $crate::Notification,
+            ) -> futures_util::future::BoxFuture<'a, $crate::services::sink::SinkReport> {
+                Box::pin(async move {
+                    $crate::services::sink::SinkReport::from_result(
+                        self.send_notification(payload, notification).await,
+                    )
+                })
+            }
+        }
+    };
+}
+pub(crate) use chat_sink;
+
+/// The chat outputs configured through the environment. Fails when one of
+/// them is configured with an invalid filter, or invalid Telegram chats.
+pub fn chat_sinks_from_env(
+    client: &reqwest::Client,
+) -> Result<Vec<Arc<dyn NotificationSink>>, String> {
+    let mut sinks: Vec<Arc<dyn NotificationSink>> = Vec::new();
+
+    if let Some(slack) = SlackService::from_env(client.clone())? {
+        sinks.push(Arc::new(slack));
+    }
+    if let Some(discord) = DiscordService::from_env(client.clone())? {
+        sinks.push(Arc::new(discord));
+    }
+    if let Some(ntfy) = NtfyService::from_env(client.clone())? {
+        sinks.push(Arc::new(ntfy));
+    }
+    if let Some(gotify) = GotifyService::from_env(client.clone())? {
+        sinks.push(Arc::new(gotify));
+    }
+    if let Some(telegram) = TelegramService::from_env(client.clone())? {
+        sinks.push(Arc::new(telegram));
+    }
+    if let Some(teams) = TeamsService::from_env(client.clone())? {
+        sinks.push(Arc::new(teams));
+    }
+
+    Ok(sinks)
+}
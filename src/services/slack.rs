@@ -0,0 +1,136 @@
+use std::env;
+
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventFamily},
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+    utils::truncate,
+};
+
+/// Posts notifications to a Slack incoming webhook as Block Kit messages.
+pub struct SlackService {
+    pub webhook_url: String,
+    pub filter: EventFilter,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+impl SlackService {
+    pub fn new(webhook_url: String, client: reqwest::Client) -> Self {
+        Self {
+            webhook_url,
+            filter: EventFilter::default(),
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `SLACK_WEBHOOK_URL` and the optional `SLACK_FILTER`. Returns
+    /// `None` when Slack is not configured.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(webhook_url) = env::var("SLACK_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            filter: EventFilter::from_env("SLACK_FILTER")?,
+            ..Self::new(webhook_url, client)
+        }))
+    }
+
+    /// Builds the Block Kit message. The colour bar comes from the legacy
+    /// attachment wrapper, which is the only way to colour a webhook message.
+    pub fn message(payload: &WebhookPayload, notification: &Notification) -> Value {
+        let family = event_parser::event_family(payload.event.as_deref().unwrap_or(""));
+        let (emoji, color) = match family {
+            EventFamily::Success => (":white_check_mark:", "#2eb886"),
+            EventFamily::Failure => (":rotating_light:", "#e01e5a"),
+            EventFamily::Info => (":information_source:", "#439fe0"),
+        };
+        let title = format!("{} {}", emoji, notification.title);
+
+        let mut blocks = vec![
+            json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": truncate(&title, 150), "emoji": true },
+            }),
+            json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": truncate(&escape(&notification.body), 3000) },
+            }),
+        ];
+
+        let fields = event_parser::payload_fields(payload)
+            .into_iter()
+            .filter(|(label, _)| matches!(*label, "Server" | "Project" | "Application"))
+            .map(|(label, value)| {
+                json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", label, escape(&value)) })
+            })
+            .collect::<Vec<_>>();
+        if !fields.is_empty() {
+            blocks.push(json!({ "type": "section", "fields": fields }));
+        }
+
+        let buttons = event_parser::links(payload)
+            .into_iter()
+            .map(|link| {
+                json!({
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": link.label },
+                    "url": link.url,
+                })
+            })
+            .collect::<Vec<_>>();
+        if !buttons.is_empty() {
+            blocks.push(json!({ "type": "actions", "elements": buttons }));
+        }
+
+        json!({
+            "text": title,
+            "attachments": [{ "color": color, "blocks": blocks }],
+        })
+    }
+
+    /// Posts the notification unless the filter rejects the webhook, in
+    /// which case `Ok(false)` is returned.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        if !self.filter.matches(payload) {
+            debug!("Webhook filtered out for Slack");
+            return Ok(false);
+        }
+
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let message = Self::message(&webhook_payload, notification).to_string();
+
+        output::send("slack", &self.retry_policy, || {
+            self.client
+                .post(&self.webhook_url)
+                .header("Content-Type", "application/json")
+                .body(message.clone())
+        })
+        .await?;
+
+        Ok(true)
+    }
+}
+
+chat_sink!(SlackService, "slack");
+
+/// Escapes the characters Slack treats as control sequences in `mrkdwn`.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,126 @@
+use std::env;
+
+use serde_json::{Value, json};
+use tracing::debug;
+
+use crate::{
+    Notification, WebhookPayload,
+    event_parser::{self, EventFamily},
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+};
+
+/// Posts notifications as Adaptive Cards to a Microsoft Teams workflow
+/// webhook, or any other endpoint that accepts the same message format.
+pub struct TeamsService {
+    pub webhook_url: String,
+    pub filter: EventFilter,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+impl TeamsService {
+    pub fn new(webhook_url: String, client: reqwest::Client) -> Self {
+        Self {
+            webhook_url,
+            filter: EventFilter::default(),
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `TEAMS_WEBHOOK_URL` and the optional `TEAMS_FILTER`. Returns
+    /// `None` when Teams is not configured.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(webhook_url) = env::var("TEAMS_WEBHOOK_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            filter: EventFilter::from_env("TEAMS_FILTER")?,
+            ..Self::new(webhook_url, client)
+        }))
+    }
+
+    /// Builds the Adaptive Card: a coloured title, the body, a fact set of
+    /// the payload fields and an `Action.OpenUrl` button for every link.
+    pub fn card(payload: &WebhookPayload, notification: &Notification) -> Value {
+        let event = payload.event.as_deref().unwrap_or("unknown");
+        let color = match event_parser::event_family(event) {
+            EventFamily::Success => "Good",
+            EventFamily::Failure => "Attention",
+            EventFamily::Info => "Accent",
+        };
+
+        let mut facts = vec![json!({ "title": "Event", "value": event })];
+        facts.extend(
+            event_parser::payload_fields(payload)
+                .into_iter()
+                .map(|(title, value)| json!({ "title": title, "value": value })),
+        );
+
+        let actions = event_parser::links(payload)
+            .into_iter()
+            .map(|link| json!({ "type": "Action.OpenUrl", "title": link.label, "url": link.url }))
+            .collect::<Vec<_>>();
+
+        json!({
+            "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+            "type": "AdaptiveCard",
+            "version": "1.4",
+            "body": [
+                {
+                    "type": "TextBlock",
+                    "text": notification.title,
+                    "size": "Medium",
+                    "weight": "Bolder",
+                    "color": color,
+                    "wrap": true,
+                },
+                { "type": "TextBlock", "text": notification.body, "wrap": true },
+                { "type": "FactSet", "facts": facts },
+            ],
+            "actions": actions,
+        })
+    }
+
+    /// Posts the notification unless the filter rejects the webhook, in
+    /// which case `Ok(false)` is returned.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        if !self.filter.matches(payload) {
+            debug!("Webhook filtered out for Teams");
+            return Ok(false);
+        }
+
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let message = json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "contentUrl": null,
+                "content": Self::card(&webhook_payload, notification),
+            }],
+        })
+        .to_string();
+
+        output::send("teams", &self.retry_policy, || {
+            self.client
+                .post(&self.webhook_url)
+                .header("Content-Type", "application/json")
+                .body(message.clone())
+        })
+        .await?;
+
+        Ok(true)
+    }
+}
+
+chat_sink!(TeamsService, "teams");
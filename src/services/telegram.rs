@@ -0,0 +1,202 @@
+use std::env;
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+use tracing::{debug, error};
+
+use crate::{
+    Notification, WebhookPayload, event_parser,
+    filter::EventFilter,
+    services::{output, retry::RetryPolicy, sink::chat_sink},
+};
+
+/// Sends notifications through a Telegram bot to one or more chats.
+pub struct TelegramService {
+    pub api_url: String,
+    pub bot_token: String,
+    pub chats: Vec<TelegramChat>,
+    pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+}
+
+/// A chat the bot posts to, with the events it should receive.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TelegramChat {
+    /// Numeric chat id, or `@channelusername` for public channels.
+    #[serde(deserialize_with = "string_or_number")]
+    pub chat_id: String,
+    #[serde(default)]
+    pub filter: EventFilter,
+}
+
+impl TelegramChat {
+    pub fn new(chat_id: impl Into<String>) -> Self {
+        Self {
+            chat_id: chat_id.into(),
+            filter: EventFilter::default(),
+        }
+    }
+}
+
+impl TelegramService {
+    pub fn new(
+        api_url: String,
+        bot_token: String,
+        chats: Vec<TelegramChat>,
+        client: reqwest::Client,
+    ) -> Self {
+        Self {
+            api_url,
+            bot_token,
+            chats,
+            client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads `TELEGRAM_BOT_TOKEN`, the chats from `TELEGRAM_CHAT_IDS` and the
+    /// `TELEGRAM_CHATS` JSON array, and the optional `TELEGRAM_API_URL`.
+    /// Returns `None` when no bot token or chat is configured, and fails
+    /// when `TELEGRAM_CHATS` is invalid.
+    pub fn from_env(client: reqwest::Client) -> Result<Option<Self>, String> {
+        let Some(bot_token) = env::var("TELEGRAM_BOT_TOKEN")
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        let mut chats = env::var("TELEGRAM_CHAT_IDS")
+            .unwrap_or("".to_string())
+            .split(',')
+            .map(|chat_id| chat_id.trim().to_string())
+            .filter(|chat_id| !chat_id.is_empty())
+            .map(TelegramChat::new)
+            .collect::<Vec<TelegramChat>>();
+        if let Ok(json) = env::var("TELEGRAM_CHATS")
+            && !json.trim().is_empty()
+        {
+            let parsed = serde_json::from_str::<Vec<TelegramChat>>(&json)
+                .map_err(|e| format!("Invalid TELEGRAM_CHATS: {}", e))?;
+            chats.extend(parsed);
+        }
+        if chats.is_empty() {
+            error!("TELEGRAM_BOT_TOKEN is set but no chat is configured.");
+            return Ok(None);
+        }
+
+        let api_url = env::var("TELEGRAM_API_URL")
+            .ok()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .unwrap_or("https://api.telegram.org".to_string());
+
+        Ok(Some(Self::new(api_url, bot_token, chats, client)))
+    }
+
+    /// Builds the `sendMessage` request for a chat: the bold title and the
+    /// body in MarkdownV2, with a button for every link in the payload.
+    pub fn message(chat_id: &str, payload: &WebhookPayload, notification: &Notification) -> Value {
+        let text = format!(
+            "*{}*\n\n{}",
+            escape_markdown(&notification.title),
+            escape_markdown(&notification.body)
+        );
+        let mut message = json!({
+            "chat_id": chat_id,
+            "text": text,
+            "parse_mode": "MarkdownV2",
+            "link_preview_options": { "is_disabled": true },
+        });
+
+        let buttons = event_parser::links(payload)
+            .into_iter()
+            .map(|link| vec![json!({ "text": link.label, "url": link.url })])
+            .collect::<Vec<_>>();
+        if !buttons.is_empty() {
+            message["reply_markup"] = json!({ "inline_keyboard": buttons });
+        }
+
+        message
+    }
+
+    /// Sends the notification to every chat whose filter accepts the webhook.
+    /// Fails when any chat could not be reached, and returns `Ok(false)` when
+    /// every chat filtered the webhook out.
+    pub async fn send_notification(
+        &self,
+        payload: &Value,
+        notification: &Notification,
+    ) -> Result<bool, String> {
+        let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap_or_default();
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            self.api_url.trim_end_matches('/'),
+            self.bot_token
+        );
+
+        let sends = self.chats.iter().filter_map(|chat| {
+            if !chat.filter.matches(payload) {
+                debug!(chat_id = %chat.chat_id, "Webhook filtered out for Telegram chat");
+                return None;
+            }
+
+            let message = Self::message(&chat.chat_id, &webhook_payload, notification).to_string();
+            let url = &url;
+            Some(async move {
+                output::send("telegram", &self.retry_policy, || {
+                    self.client
+                        .post(url)
+                        .header("Content-Type", "application/json")
+                        .body(message.clone())
+                })
+                .await
+                .map_err(|e| format!("chat {}: {}", chat.chat_id, e))
+            })
+        });
+
+        let results = join_all(sends).await;
+        if results.is_empty() {
+            return Ok(false);
+        }
+
+        let errors = results
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<String>>();
+        if errors.is_empty() {
+            Ok(true)
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+chat_sink!(TelegramService, "telegram");
+
+/// Escapes every character with a meaning in Telegram's MarkdownV2.
+pub fn escape_markdown(text: &str) -> String {
+    const SPECIAL: &[char] = &[
+        '\\', '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.',
+        '!',
+    ];
+
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(value) => Ok(value),
+        Value::Number(value) => Ok(value.to_string()),
+        _ => Err(serde::de::Error::custom("expected a chat id")),
+    }
+}
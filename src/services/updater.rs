@@ -14,6 +14,9 @@ pub struct UpdaterService {
     pub last_check_time: Option<SystemTime>,
     pub check_for_updates_interval: u64,
     pub update_check_url: &'static str,
+    /// Device groups that receive the update notification. Every token does
+    /// when empty.
+    pub notify_groups: Vec<String>,
     current_version: &'static str,
     client: reqwest::Client,
 }
@@ -49,6 +52,7 @@ impl Default for UpdaterService {
             notification_sent: false,
             last_check_time: None,
             check_for_updates_interval: 86400,
+            notify_groups: Vec::new(),
             client: reqwest::Client::new(),
         }
     }
@@ -134,7 +138,12 @@ impl UpdaterService {
             ),
             data: release,
         };
-        expo.send_notification(notification).await;
+        if self.notify_groups.is_empty() {
+            expo.send_notification(notification).await;
+        } else {
+            expo.send_notification_to_groups(&self.notify_groups, notification)
+                .await;
+        }
         self.notification_sent = true;
         Ok(())
     }
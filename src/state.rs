@@ -1,7 +1,34 @@
-use crate::services::{expo::ExpoService, repeater::WebhookRepeaterService};
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    routing::Routes,
+    services::{
+        admin::AdminAuth, deployment_poller::PollerStatus, endpoints::WebhookEndpoint,
+        expo::ExpoService, history::HistoryStore, repeater::WebhookRepeaterService,
+        sink::NotificationSink,
+    },
+};
 
 pub struct AppState {
-    pub expo: ExpoService,
-    pub repeater: WebhookRepeaterService,
+    /// Every output a webhook is delivered to, including `expo` and `repeater`.
+    pub sinks: Vec<Arc<dyn NotificationSink>>,
+    /// Decides which of the `sinks` receive an event.
+    pub routes: Routes,
+    /// Kept alongside `sinks` for token and target status reporting.
+    pub expo: Arc<ExpoService>,
+    pub repeater: Arc<WebhookRepeaterService>,
+    pub history: Arc<HistoryStore>,
+    pub endpoints: Vec<Arc<WebhookEndpoint>>,
+    pub poller_status: RwLock<PollerStatus>,
+    pub admin: Option<AdminAuth>,
     pub http_client: reqwest::Client,
 }
+
+impl AppState {
+    pub fn endpoint(&self, name: &str) -> Option<Arc<WebhookEndpoint>> {
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == name)
+            .cloned()
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+
+use crate::Notification;
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z0-9_.]+)\s*\}\}").expect("valid regex"));
+
+/// Values available to templates: the rendered notification `title` and
+/// `body`, the original webhook as `payload`, the name of the `endpoint`
+/// that received it and the Coolify `instance` a polled event came from.
+pub struct TemplateContext<'a> {
+    pub notification: &'a Notification,
+    pub payload: &'a Value,
+}
+
+impl TemplateContext<'_> {
+    /// Looks up a placeholder such as `title`, `payload` or
+    /// `payload.server_name`.
+    pub fn lookup(&self, path: &str) -> Option<Value> {
+        let mut keys = path.split('.');
+
+        match keys.next()? {
+            "title" => Some(Value::String(self.notification.title.clone())),
+            "body" => Some(Value::String(self.notification.body.clone())),
+            "endpoint" => self.payload.get("endpoint").cloned(),
+            "instance" => self.payload.get("instance").cloned(),
+            "payload" => keys
+                .try_fold(self.payload, |value, key| value.get(key))
+                .cloned(),
+            _ => None,
+        }
+    }
+
+    /// Replaces `{{placeholder}}`s in a string. Missing values render as an
+    /// empty string, other non-string values as JSON.
+    pub fn render_str(&self, template: &str) -> String {
+        PLACEHOLDER
+            .replace_all(template, |captures: &Captures| {
+                match self.lookup(&captures[1]) {
+                    Some(Value::String(value)) => value,
+                    Some(Value::Null) | None => String::new(),
+                    Some(value) => value.to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Renders every string in a JSON template. A string that consists of a
+    /// single placeholder is replaced by the referenced value as is, so
+    /// numbers, objects and arrays keep their type.
+    pub fn render(&self, template: &Value) -> Value {
+        match template {
+            Value::String(template) => match PLACEHOLDER.captures(template) {
+                Some(captures) if captures[0].len() == template.len() => {
+                    self.lookup(&captures[1]).unwrap_or(Value::Null)
+                }
+                _ => Value::String(self.render_str(template)),
+            },
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|item| self.render(item)).collect())
+            }
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.render(value)))
+                    .collect::<Map<String, Value>>(),
+            ),
+            value => value.clone(),
+        }
+    }
+}
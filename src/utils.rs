@@ -1,5 +1,10 @@
 use regex::Regex;
 use std::env;
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static EXPO_PUSH_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"ExponentPushToken\[(?<token>[^\]]+)\]").expect("valid regex"));
 
 pub fn parse_expo_push_tokens() -> Result<Vec<String>, String> {
     let Ok(expo_push_tokens) = env::var("EXPO_PUSH_TOKENS") else {
@@ -10,15 +15,9 @@ pub fn parse_expo_push_tokens() -> Result<Vec<String>, String> {
         .split(',')
         .map(str::to_string)
         .collect::<Vec<String>>();
-    let re = Regex::new(r"ExponentPushToken\[(?<token>[^\]]+)\]");
-
-    let Ok(re) = &re else {
-        return Err("Failed to create regex for Expo push tokens validation.".to_string());
-    };
-
     let valid_tokens = tokens
         .iter()
-        .filter(|token| re.is_match(token))
+        .filter(|token| is_expo_push_token(token))
         .map(|token| token.to_string())
         .collect::<Vec<String>>();
 
@@ -28,3 +27,75 @@ pub fn parse_expo_push_tokens() -> Result<Vec<String>, String> {
 
     Ok(valid_tokens)
 }
+
+pub fn is_expo_push_token(token: &str) -> bool {
+    EXPO_PUSH_TOKEN.is_match(token)
+}
+
+/// Compares secrets without leaking how much of them matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Formats a duration for notifications, e.g. `45s`, `2m 13s` or `1h 5m`.
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats unix seconds as an RFC 3339 UTC timestamp, e.g.
+/// `2024-05-01T12:00:00Z`.
+pub fn rfc3339(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Shortens text to at most `max_chars` characters, ending it with `…` when
+/// it had to be cut.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated = text
+        .chars()
+        .take(max_chars.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
@@ -0,0 +1,30 @@
+use axum::http::{HeaderMap, HeaderValue, header};
+use coolify_expo_notification_relay::services::admin::AdminAuth;
+
+fn headers(authorization: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_str(authorization).unwrap(),
+    );
+    headers
+}
+
+#[test]
+fn bearer_and_basic_credentials_are_accepted() {
+    let admin = AdminAuth::new("secret".to_string());
+
+    assert!(admin.is_authorized(&headers("Bearer secret")));
+    // "admin:secret" in base64
+    assert!(admin.is_authorized(&headers("Basic YWRtaW46c2VjcmV0")));
+}
+
+#[test]
+fn missing_or_wrong_credentials_are_rejected() {
+    let admin = AdminAuth::new("secret".to_string());
+
+    assert!(!admin.is_authorized(&HeaderMap::new()));
+    assert!(!admin.is_authorized(&headers("Bearer wrong")));
+    // "admin:wrong" in base64
+    assert!(!admin.is_authorized(&headers("Basic YWRtaW46d3Jvbmc=")));
+}
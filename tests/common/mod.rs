@@ -0,0 +1,130 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, Method},
+    response::IntoResponse,
+    routing::any,
+};
+use coolify_expo_notification_relay::{
+    ExpoService, HistoryStore, Notification, NotificationSink, WebhookRepeaterService,
+    routing::Routes,
+    services::{deployment_poller::PollerStatus, sink::SinkReport},
+    state::AppState,
+};
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// Serves `app` on a free local port and returns its base URL, e.g.
+/// `http://127.0.0.1:4321`.
+pub async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// A request received by a [`mock`] server.
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub method: Method,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Received {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+pub type Requests = Arc<Mutex<Vec<Received>>>;
+
+/// Starts a server that records every request to `path` and answers the
+/// `n`th one, counting from 1, with `respond(n)`. Returns the base URL.
+pub async fn mock<F, R>(path: &str, respond: F) -> (String, Requests)
+where
+    F: Fn(usize) -> R + Clone + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    let requests: Requests = Arc::default();
+    let app = Router::new()
+        .route(
+            path,
+            any(
+                move |State(requests): State<Requests>,
+                      method: Method,
+                      headers: HeaderMap,
+                      body: Bytes| {
+                    let respond = respond.clone();
+                    async move {
+                        let count = {
+                            let mut requests = requests.lock().unwrap();
+                            requests.push(Received {
+                                method,
+                                headers,
+                                body,
+                            });
+                            requests.len()
+                        };
+                        respond(count).into_response()
+                    }
+                },
+            ),
+        )
+        .with_state(requests.clone());
+
+    (serve(app).await, requests)
+}
+
+/// Forwards everything it receives to a channel.
+pub struct StubSink(pub mpsc::UnboundedSender<(Value, Notification)>);
+
+impl NotificationSink for StubSink {
+    fn name(&self) -> &'static str {
+        "stub"
+    }
+
+    fn deliver<'a>(
+        &'a self,
+        payload: &'a Value,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, SinkReport> {
+        Box::pin(async move {
+            self.0
+                .send((payload.clone(), notification.clone()))
+                .unwrap();
+            SinkReport::default()
+        })
+    }
+}
+
+/// State with a [`StubSink`] as its only output and an in-memory history,
+/// together with the channel the sink delivers to.
+pub fn app_state() -> (AppState, mpsc::UnboundedReceiver<(Value, Notification)>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let client = reqwest::Client::new();
+    let state = AppState {
+        sinks: vec![Arc::new(StubSink(sender))],
+        routes: Routes::default(),
+        expo: Arc::new(ExpoService::new(
+            Vec::new(),
+            "http://127.0.0.1:9".to_string(),
+            client.clone(),
+        )),
+        repeater: Arc::new(WebhookRepeaterService::new(Vec::new(), client.clone())),
+        history: Arc::new(HistoryStore::open(":memory:", 30).unwrap()),
+        endpoints: Vec::new(),
+        poller_status: RwLock::new(PollerStatus::default()),
+        admin: None,
+        http_client: client,
+    };
+
+    (state, receiver)
+}
@@ -0,0 +1,377 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    routing::get,
+};
+use coolify_expo_notification_relay::{
+    DeploymentPollerService, Notification,
+    services::{
+        deployment_poller::{InstanceStatus, PollerStatus},
+        history::PolledDeployment,
+    },
+    state::AppState,
+    utils::unix_timestamp,
+};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+fn state() -> (
+    Arc<AppState>,
+    mpsc::UnboundedReceiver<(Value, Notification)>,
+) {
+    let (state, receiver) = common::app_state();
+    (Arc::new(state), receiver)
+}
+
+/// Serves an empty list of deployments to requests with the token `token`,
+/// and the final status of deployments: `ok` finished, `bad` failed, `gone`
+/// cannot be looked up, `odd` has no status and `slow` is still in progress.
+async fn coolify() -> String {
+    let app = Router::new()
+        .route(
+            "/api/v1/deployments",
+            get(|headers: HeaderMap| async move {
+                match headers.get("authorization") {
+                    Some(value) if value == "Bearer token" => Ok(Json(json!([]))),
+                    _ => Err(StatusCode::UNAUTHORIZED),
+                }
+            }),
+        )
+        .route(
+            "/api/v1/deployments/{uuid}",
+            get(|Path(uuid): Path<String>| async move {
+                let status = match uuid.as_str() {
+                    "ok" => "finished",
+                    "bad" => "failed",
+                    "gone" => return Err(StatusCode::BAD_GATEWAY),
+                    "odd" => "",
+                    _ => "in_progress",
+                };
+                Ok(Json(json!({ "deployment_uuid": uuid, "status": status })))
+            }),
+        );
+    common::serve(app).await
+}
+
+fn deployment(uuid: &str) -> Value {
+    json!({ "deployment_uuid": uuid, "application_name": "shop", "project": "web" })
+}
+
+#[tokio::test]
+async fn finished_deployments_are_reported_with_their_duration() {
+    let (state, mut receiver) = state();
+    let mut poller = DeploymentPollerService::new(
+        coolify().await,
+        "token".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+
+    poller
+        .handle_deployments(
+            &state,
+            vec![deployment("ok"), deployment("bad"), deployment("slow")],
+        )
+        .await;
+    for _ in 0..3 {
+        let (payload, _) = receiver.recv().await.unwrap();
+        assert_eq!(payload["event"], "deployment_started");
+    }
+
+    poller.handle_deployments(&state, Vec::new()).await;
+
+    let mut finished = Vec::new();
+    for _ in 0..2 {
+        let (payload, notification) = receiver.recv().await.unwrap();
+        // Durations are measured in whole seconds of the wall clock.
+        assert!(payload["duration_secs"].as_u64().unwrap() <= 1);
+        finished.push((
+            payload["deployment_uuid"].as_str().unwrap().to_string(),
+            payload["event"].as_str().unwrap().to_string(),
+            notification.body,
+        ));
+    }
+    finished.sort();
+    assert_eq!(finished[0].0, "bad");
+    assert_eq!(finished[0].1, "deployment_failed");
+    assert!(finished[0].2.contains(" failed after "));
+    assert_eq!(finished[1].0, "ok");
+    assert_eq!(finished[1].1, "deployment_success");
+    assert!(finished[1].2.contains(" in "));
+
+    // The deployment that is still running is checked again on the next poll
+    // instead of being reported.
+    assert!(receiver.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn tracked_deployments_survive_a_restart() {
+    let (state, mut receiver) = state();
+    let saved = |uuid: &str, age: u64| PolledDeployment {
+        instance: None,
+        uuid: uuid.to_string(),
+        first_seen: unix_timestamp() - age,
+        payload: deployment(uuid),
+    };
+    state
+        .history
+        .record_polled_deployment(&saved("ok", 90))
+        .unwrap();
+    state
+        .history
+        .record_polled_deployment(&saved("bad", 3 * 24 * 60 * 60))
+        .unwrap();
+
+    let mut poller = DeploymentPollerService::new(
+        coolify().await,
+        "token".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    poller.restore(&state.history);
+
+    // The stale entry is expired and the other one is not notified again.
+    let remaining = state.history.polled_deployments(None).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].uuid, "ok");
+    poller
+        .handle_deployments(&state, vec![deployment("ok")])
+        .await;
+    assert!(receiver.try_recv().is_err());
+
+    poller.handle_deployments(&state, Vec::new()).await;
+    let (payload, _) = receiver.recv().await.unwrap();
+    assert_eq!(payload["event"], "deployment_success");
+    assert!(payload["duration_secs"].as_u64().unwrap() >= 90);
+    assert!(state.history.polled_deployments(None).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_lookups_are_retried_and_unknown_statuses_are_dropped() {
+    let (state, mut receiver) = state();
+    let mut poller = DeploymentPollerService::new(
+        coolify().await,
+        "token".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    poller
+        .handle_deployments(&state, vec![deployment("gone"), deployment("odd")])
+        .await;
+    receiver.recv().await.unwrap();
+    receiver.recv().await.unwrap();
+
+    let tracked = |state: &AppState| {
+        let mut uuids = state
+            .history
+            .polled_deployments(None)
+            .unwrap()
+            .into_iter()
+            .map(|saved| saved.uuid)
+            .collect::<Vec<_>>();
+        uuids.sort();
+        uuids
+    };
+    for _ in 0..9 {
+        poller.handle_deployments(&state, Vec::new()).await;
+    }
+    assert_eq!(tracked(&state), vec!["gone", "odd"]);
+
+    poller.handle_deployments(&state, Vec::new()).await;
+    assert_eq!(tracked(&state), vec!["gone"]);
+    assert_eq!(poller.next_delay(), Duration::from_secs(3));
+    assert!(receiver.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn instance_names_are_added_to_events_and_notifications() {
+    assert!(
+        DeploymentPollerService::parse_instances(
+            r#"[{"name": "staging", "url": "https://a", "token": "t"},
+                {"name": "staging", "url": "https://b", "token": "t"}]"#
+        )
+        .is_err()
+    );
+    assert!(
+        DeploymentPollerService::parse_instances(
+            r#"[{"name": "", "url": "https://a", "token": "t"}]"#
+        )
+        .is_err()
+    );
+
+    let (state, mut receiver) = state();
+    let url = coolify().await;
+    let mut pollers = DeploymentPollerService::parse_instances(&format!(
+        r#"[{{"name": "staging", "url": "{}", "token": "t", "poll_seconds": 30}}]"#,
+        url
+    ))
+    .unwrap();
+    assert_eq!(pollers[0].poll_interval_secs, 30);
+    assert_eq!(pollers[0].api_endpoint, "api/v1/deployments");
+
+    pollers[0]
+        .handle_deployments(&state, vec![deployment("ok")])
+        .await;
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert_eq!(payload["instance"], "staging");
+    assert_eq!(notification.title, "[staging] Deployment Started");
+
+    pollers[0].handle_deployments(&state, Vec::new()).await;
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert_eq!(payload["event"], "deployment_success");
+    assert_eq!(payload["instance"], "staging");
+    assert_eq!(notification.title, "[staging] Deployment Success");
+}
+
+#[tokio::test]
+async fn polling_backs_off_on_failures_and_speeds_up_while_deploying() {
+    let (state, mut receiver) = state();
+    let mut poller = DeploymentPollerService::new(
+        coolify().await,
+        "revoked".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    poller.max_backoff_secs = 60;
+    poller.outage_notify_secs = 0;
+
+    let error = poller.check_for_deployments().await.unwrap_err();
+    assert!(error.contains("401"));
+    for expected in [20, 40, 60, 60] {
+        poller.handle_failure(&state, error.clone()).await;
+        assert_eq!(poller.next_delay(), Duration::from_secs(expected));
+    }
+
+    poller.handle_success(&state).await;
+    assert_eq!(poller.next_delay(), Duration::from_secs(10));
+    poller
+        .handle_deployments(&state, vec![deployment("slow")])
+        .await;
+    assert_eq!(poller.next_delay(), Duration::from_secs(3));
+
+    receiver.recv().await.unwrap();
+    assert!(receiver.try_recv().is_err());
+
+    // Intervals of zero are polled once a second, still backing off.
+    poller.poll_interval_secs = 0;
+    poller.active_poll_interval_secs = 0;
+    assert_eq!(poller.next_delay(), Duration::from_secs(1));
+    poller.handle_failure(&state, error).await;
+    assert_eq!(poller.next_delay(), Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn api_outages_are_notified_once_and_on_recovery() {
+    let (state, mut receiver) = state();
+    let mut poller = DeploymentPollerService::new(
+        coolify().await,
+        "revoked".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    poller.outage_notify_secs = 1;
+
+    let error = poller.check_for_deployments().await.unwrap_err();
+    poller.handle_failure(&state, error.clone()).await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    poller.handle_failure(&state, error.clone()).await;
+    poller.handle_failure(&state, error).await;
+
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert_eq!(payload["event"], "coolify_api_unreachable");
+    assert_eq!(notification.title, "Coolify API Unreachable");
+    assert!(notification.body.contains("401"));
+    assert!(receiver.try_recv().is_err());
+
+    poller.api_token = "token".to_string();
+    poller.check_for_deployments().await.unwrap();
+    poller.handle_success(&state).await;
+    let (payload, _) = receiver.recv().await.unwrap();
+    assert_eq!(payload["event"], "coolify_api_reachable");
+    assert!(payload["duration_secs"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn instances_track_their_own_deployments() {
+    let (state, mut receiver) = state();
+    let url = coolify().await;
+    let mut default = DeploymentPollerService::new(
+        url.clone(),
+        "token".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    let mut staging = DeploymentPollerService::new(
+        url,
+        "token".to_string(),
+        "api/v1/deployments".to_string(),
+        10,
+    );
+    staging.instance = Some("staging".to_string());
+
+    // The same deployment UUID seen on both instances is tracked twice.
+    default
+        .handle_deployments(&state, vec![deployment("ok")])
+        .await;
+    staging
+        .handle_deployments(&state, vec![deployment("ok")])
+        .await;
+    receiver.recv().await.unwrap();
+    receiver.recv().await.unwrap();
+
+    staging.handle_deployments(&state, Vec::new()).await;
+    let (payload, _) = receiver.recv().await.unwrap();
+    assert_eq!(payload["instance"], "staging");
+
+    assert!(
+        state
+            .history
+            .polled_deployments(Some("staging"))
+            .unwrap()
+            .is_empty()
+    );
+    let remaining = state.history.polled_deployments(None).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].uuid, "ok");
+}
+
+#[test]
+fn status_keeps_the_default_instance_at_the_top_level() {
+    let status = PollerStatus {
+        enabled: true,
+        instances: vec![
+            InstanceStatus {
+                poll_interval_secs: 10,
+                last_error: Some("Coolify API is down".to_string()),
+                in_progress: vec!["ok".to_string()],
+                ..InstanceStatus::default()
+            },
+            InstanceStatus {
+                name: Some("staging".to_string()),
+                poll_interval_secs: 30,
+                ..InstanceStatus::default()
+            },
+        ],
+    };
+
+    let json = serde_json::to_value(&status).unwrap();
+    assert_eq!(json["enabled"], true);
+    assert_eq!(json["poll_interval_secs"], 10);
+    assert_eq!(json["last_error"], "Coolify API is down");
+    assert_eq!(json["in_progress"], json!(["ok"]));
+    assert_eq!(json["instances"][1]["name"], "staging");
+
+    // Without a default instance, only the instances are listed.
+    let status = PollerStatus {
+        enabled: true,
+        instances: status.instances[1..].to_vec(),
+    };
+    let json = serde_json::to_value(&status).unwrap();
+    assert!(json.get("poll_interval_secs").is_none());
+    assert_eq!(json["instances"][0]["poll_interval_secs"], 30);
+}
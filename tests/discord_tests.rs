@@ -0,0 +1,86 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use coolify_expo_notification_relay::{
+    Notification,
+    services::{discord::DiscordService, retry::RetryPolicy},
+    utils,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn embed_is_resent_after_discord_retry_after() {
+    // Rate limits the first request.
+    let (url, received) = common::mock("/", |count| {
+        if count == 1 {
+            let body = json!({ "message": "You are being rate limited.", "retry_after": 0.2, "global": false });
+            (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+        } else {
+            StatusCode::NO_CONTENT.into_response()
+        }
+    })
+    .await;
+    let mut discord = DiscordService::new(format!("{}/", url), reqwest::Client::new());
+    discord.retry_policy = RetryPolicy::new(3, Duration::from_millis(1));
+    let notification = Notification {
+        title: "Backup Success".to_string(),
+        body: "Database backup completed".to_string(),
+    };
+
+    let started_at = Instant::now();
+    discord
+        .send_notification(
+            &json!({ "event": "backup_success", "server_name": "prod", "database_name": "db" }),
+            &notification,
+        )
+        .await
+        .unwrap();
+
+    assert!(started_at.elapsed() >= Duration::from_millis(200));
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+
+    let message = received[1].json();
+    let embed = &message["embeds"][0];
+    assert_eq!(embed["title"], "Backup Success");
+    assert_eq!(embed["description"], "Database backup completed");
+    assert_eq!(embed["color"], 0x2ecc71);
+    assert_eq!(embed["fields"][0]["name"], "Server");
+    assert_eq!(embed["fields"][1]["value"], "db");
+    assert!(embed["timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+#[test]
+fn embed_timestamps_are_rfc3339() {
+    assert_eq!(utils::rfc3339(0), "1970-01-01T00:00:00Z");
+    assert_eq!(utils::rfc3339(1_714_564_800), "2024-05-01T12:00:00Z");
+    assert_eq!(utils::rfc3339(951_868_799), "2000-02-29T23:59:59Z");
+}
+
+#[tokio::test]
+async fn out_of_range_retry_after_does_not_panic() {
+    let (url, received) = common::mock("/", |count| {
+        if count == 1 {
+            let body = json!({ "message": "You are being rate limited.", "retry_after": 1e30 });
+            (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response()
+        } else {
+            StatusCode::NO_CONTENT.into_response()
+        }
+    })
+    .await;
+    let mut discord = DiscordService::new(format!("{}/", url), reqwest::Client::new());
+    discord.retry_policy = RetryPolicy::new(2, Duration::from_millis(1));
+    let notification = Notification {
+        title: "Backup Success".to_string(),
+        body: "Database backup completed".to_string(),
+    };
+
+    discord
+        .send_notification(&json!({ "event": "backup_success" }), &notification)
+        .await
+        .unwrap();
+
+    assert_eq!(received.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{Router, routing::post};
+use coolify_expo_notification_relay::{
+    WebhookRepeaterService,
+    routing::Routes,
+    services::{self, endpoints::WebhookEndpoint},
+};
+use serde_json::json;
+
+#[test]
+fn endpoints_are_parsed_and_validated() {
+    let client = reqwest::Client::new();
+    let repeater = WebhookRepeaterService::new(Vec::new(), client.clone());
+
+    let endpoints = WebhookEndpoint::parse(
+        r#"[
+            {"name": "team-a", "secret": "s3cret", "devices": ["oncall"],
+             "repeaters": [{"url": "https://example.com/hook"}]},
+            {"name": "team-b"}
+        ]"#,
+        &client,
+        &repeater,
+    )
+    .unwrap();
+    assert_eq!(endpoints.len(), 2);
+    assert!(endpoints[0].repeater.is_some());
+    assert_eq!(endpoints[0].targets("expo"), vec!["oncall"]);
+    assert!(endpoints[1].repeater.is_none());
+
+    assert!(WebhookEndpoint::parse(r#"[{"name": "a/b"}]"#, &client, &repeater).is_err());
+    assert!(
+        WebhookEndpoint::parse(r#"[{"name": "a"}, {"name": "a"}]"#, &client, &repeater).is_err()
+    );
+}
+
+#[tokio::test]
+async fn endpoint_webhooks_require_the_secret_and_are_tagged() {
+    let endpoint = WebhookEndpoint {
+        secret: Some("s3cret".to_string()),
+        template: serde_json::from_value(json!({ "title": "[{{endpoint}}] {{title}}" })).unwrap(),
+        ..WebhookEndpoint::new("team-a")
+    };
+    let (mut state, mut receiver) = common::app_state();
+    state.endpoints = vec![Arc::new(endpoint)];
+    let url = common::serve(
+        Router::new()
+            .route("/hooks/{name}", post(services::handle_endpoint_webhook))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let post = |path: &str| {
+        client
+            .post(format!("{}{}", url, path))
+            .header("Content-Type", "application/json")
+            .body(r#"{"event":"deployment_success","application_name":"shop"}"#)
+            .send()
+    };
+    assert_eq!(post("/hooks/team-a").await.unwrap().status(), 401);
+    assert_eq!(post("/hooks/team-b").await.unwrap().status(), 404);
+    assert_eq!(
+        post("/hooks/team-a?secret=s3cret").await.unwrap().status(),
+        202
+    );
+
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert_eq!(payload["endpoint"], "team-a");
+    assert!(notification.title.starts_with("[team-a] "));
+}
+
+#[tokio::test]
+async fn global_webhooks_cannot_claim_an_endpoint() {
+    let (state, mut receiver) = common::app_state();
+    let url = common::serve(
+        Router::new()
+            .route("/", post(services::handle_webhook))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/", url))
+        .header("Content-Type", "application/json")
+        .body(
+            r#"{"event":"deployment_success","endpoint":"team-a","instance":"prod","duration_secs":5}"#,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert!(payload.get("endpoint").is_none());
+    assert!(payload.get("instance").is_none());
+    assert!(payload.get("duration_secs").is_none());
+    assert!(!notification.title.contains("prod"));
+}
+
+#[tokio::test]
+async fn routes_to_named_repeaters_use_the_global_targets() {
+    let (global_url, global) = common::mock("/", |_| "ok").await;
+    let (own_url, own) = common::mock("/", |_| "ok").await;
+    let client = reqwest::Client::new();
+    let repeater = Arc::new(WebhookRepeaterService::new(
+        WebhookRepeaterService::parse_targets(&format!(
+            r#"[{{"name": "archive", "url": "{global_url}/"}}]"#
+        ))
+        .unwrap(),
+        client.clone(),
+    ));
+    let endpoint = WebhookEndpoint::parse(
+        &format!(r#"[{{"name": "team-a", "repeaters": [{{"url": "{own_url}/"}}]}}]"#),
+        &client,
+        &repeater,
+    )
+    .unwrap()
+    .remove(0);
+    let (mut state, _receiver) = common::app_state();
+    state.sinks = vec![repeater.clone()];
+    state.repeater = repeater;
+    state.routes =
+        Routes::parse(r#"{"default": ["repeater:archive", "repeater:missing"]}"#).unwrap();
+    let endpoint = Arc::new(endpoint);
+    state.endpoints = vec![endpoint.clone()];
+
+    services::accept_webhook(
+        Arc::new(state),
+        Some(endpoint),
+        json!({ "event": "deployment_success" }),
+    );
+
+    for _ in 0..100 {
+        if !global.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(global.lock().unwrap().len(), 1);
+    assert!(own.lock().unwrap().is_empty());
+}
@@ -0,0 +1,62 @@
+use std::env;
+
+use coolify_expo_notification_relay::filter::{EventFilter, glob_match};
+use serde_json::json;
+
+fn filter(json: serde_json::Value) -> EventFilter {
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn glob_patterns_match_wildcards_case_insensitively() {
+    assert!(glob_match("*_failed", "backup_FAILED"));
+    assert!(glob_match("deployment_*", "deployment_success"));
+    assert!(glob_match("a*b*c", "axxbyyc"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("deployment_*", "backup_success"));
+    assert!(!glob_match("a*a", "a"));
+}
+
+#[test]
+fn include_requires_every_rule_and_exclude_any() {
+    let filter = filter(json!({
+        "include": { "events": ["*_failed"], "fields": { "project": ["shop"] } },
+        "exclude": { "fields": { "server_name": ["staging-*"] } }
+    }));
+
+    assert!(
+        filter.matches(
+            &json!({ "event": "backup_failed", "project": "shop", "server_name": "prod" })
+        )
+    );
+    assert!(!filter.matches(&json!({ "event": "backup_failed", "project": "blog" })));
+    assert!(!filter.matches(&json!({ "event": "deployment_success", "project": "shop" })));
+    assert!(!filter.matches(
+        &json!({ "event": "backup_failed", "project": "shop", "server_name": "staging-1" })
+    ));
+    assert!(EventFilter::default().matches(&json!({})));
+}
+
+#[test]
+fn invalid_filters_in_the_environment_are_rejected() {
+    unsafe {
+        env::set_var(
+            "TEST_INVALID_FILTER",
+            r#"{"include": {"events": "*_failed"}}"#,
+        );
+    }
+
+    let result = EventFilter::from_env("TEST_INVALID_FILTER");
+
+    unsafe {
+        env::remove_var("TEST_INVALID_FILTER");
+    }
+    let error = result.unwrap_err();
+    assert!(
+        error.starts_with("Invalid TEST_INVALID_FILTER"),
+        "{}",
+        error
+    );
+    let missing = EventFilter::from_env("TEST_MISSING_FILTER").unwrap();
+    assert!(missing.is_empty());
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use coolify_expo_notification_relay::{
+    Notification,
+    services::{gotify::GotifyService, retry::RetryPolicy},
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn message_carries_priority_and_raw_webhook_and_is_retried() {
+    // Unavailable for the first request.
+    let (url, received) = common::mock("/message", |count| {
+        if count == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    })
+    .await;
+    let mut gotify = GotifyService::new(url, "app-token".to_string(), reqwest::Client::new());
+    gotify.retry_policy = RetryPolicy::new(2, Duration::from_millis(1));
+    let payload = json!({ "event": "server_unreachable", "server_name": "prod" });
+    let notification = Notification {
+        title: "Server Unreachable".to_string(),
+        body: "prod is unreachable".to_string(),
+    };
+
+    gotify
+        .send_notification(&payload, &notification)
+        .await
+        .unwrap();
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    let message = received[1].json();
+    assert_eq!(received[1].headers["x-gotify-key"], "app-token");
+    assert_eq!(message["title"], "Server Unreachable");
+    assert_eq!(message["message"], "prod is unreachable");
+    assert_eq!(message["priority"], 10);
+    assert_eq!(message["extras"]["coolify::webhook"], payload);
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    routing::{delete, put},
+};
+use coolify_expo_notification_relay::{
+    ExpoService, HistoryStore,
+    routing::Routes,
+    services::{admin, endpoints::WebhookEndpoint, expo::ExpoNotification, groups::DeviceGroups},
+};
+use serde_json::json;
+
+const ALICE: &str = "ExponentPushToken[alice]";
+const BOB: &str = "ExponentPushToken[bob]";
+
+#[test]
+fn groups_combine_configured_and_managed_members() {
+    let configured = DeviceGroups::parse(&format!(
+        r#"{{"oncall": ["{ALICE}"], "ops": ["{ALICE}", "{BOB}"]}}"#
+    ))
+    .unwrap();
+    let groups = DeviceGroups::new(configured);
+    groups.set("oncall", vec![BOB.to_string()]);
+    groups.set("frontend", vec![BOB.to_string()]);
+
+    let oncall = groups.get("oncall").unwrap();
+    assert_eq!(oncall.members, vec![ALICE, BOB]);
+    assert_eq!(oncall.configured, vec![ALICE]);
+    assert_eq!(groups.list().len(), 3);
+
+    let (members, unknown) = groups.members(&[
+        "ops".to_string(),
+        "frontend".to_string(),
+        "missing".to_string(),
+    ]);
+    assert_eq!(members, vec![ALICE, BOB]);
+    assert_eq!(unknown, vec!["missing"]);
+
+    assert!(groups.check(&["ops".to_string(), BOB.to_string()]).is_ok());
+    let error = groups.check(&["opz".to_string()]).unwrap_err();
+    assert!(error.contains("opz"));
+
+    groups.set("frontend", Vec::new());
+    assert!(groups.get("frontend").is_none());
+
+    assert!(DeviceGroups::parse(r#"{"on call": []}"#).is_err());
+    assert!(DeviceGroups::parse(r#"{"oncall": ["not-a-token"]}"#).is_err());
+}
+
+#[test]
+fn managed_groups_are_stored_in_the_history_database() {
+    let history = HistoryStore::open(":memory:", 30).unwrap();
+    history
+        .set_device_group("oncall", &[ALICE.to_string(), BOB.to_string()])
+        .unwrap();
+    history.set_device_group("ops", &[BOB.to_string()]).unwrap();
+    history.set_device_group("ops", &[]).unwrap();
+
+    let groups = history.device_groups().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups["oncall"], vec![ALICE, BOB]);
+}
+
+#[tokio::test]
+async fn group_notifications_reach_only_their_members() {
+    let (url, received) = common::mock("/", |_| Json(json!({ "data": { "status": "ok" } }))).await;
+
+    let expo = ExpoService::new(
+        vec![ALICE.to_string(), BOB.to_string()],
+        format!("{}/", url),
+        reqwest::Client::new(),
+    );
+    expo.groups.set("oncall", vec![BOB.to_string()]);
+
+    let deliveries = expo
+        .send_notification_to_groups(
+            &["oncall".to_string()],
+            ExpoNotification {
+                title: "Server unreachable".to_string(),
+                body: "Server prod is unreachable.".to_string(),
+                data: json!({}),
+            },
+        )
+        .await;
+
+    assert_eq!(deliveries.len(), 1);
+    assert!(deliveries[0].success);
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].json()["to"], BOB);
+}
+
+#[tokio::test]
+async fn configured_groups_cannot_be_deleted_through_the_api() {
+    let (mut state, _receiver) = common::app_state();
+    let mut expo = ExpoService::new(
+        Vec::new(),
+        "http://127.0.0.1:9".to_string(),
+        reqwest::Client::new(),
+    );
+    expo.groups =
+        DeviceGroups::new(DeviceGroups::parse(&format!(r#"{{"oncall": ["{ALICE}"]}}"#)).unwrap());
+    expo.groups.set("oncall", vec![BOB.to_string()]);
+    expo.groups.set("frontend", vec![BOB.to_string()]);
+    state.expo = Arc::new(expo);
+    let state = Arc::new(state);
+    let url = common::serve(
+        Router::new()
+            .route("/admin/groups/{name}", delete(admin::delete_device_group))
+            .with_state(state.clone()),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let delete = |name: &str| {
+        client
+            .delete(format!("{}/admin/groups/{}", url, name))
+            .send()
+    };
+    assert_eq!(delete("oncall").await.unwrap().status(), 409);
+    assert_eq!(
+        state.expo.groups.get("oncall").unwrap().members,
+        vec![ALICE, BOB]
+    );
+    assert_eq!(delete("frontend").await.unwrap().status(), 204);
+    assert!(state.expo.groups.get("frontend").is_none());
+    assert_eq!(delete("frontend").await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn referenced_groups_cannot_be_removed_through_the_api() {
+    let (mut state, _receiver) = common::app_state();
+    state.expo.groups.set("oncall", vec![ALICE.to_string()]);
+    state.expo.groups.set("ops", vec![BOB.to_string()]);
+    state.routes = Routes::parse(
+        r#"{"rules": [{"match": {"events": ["backup_failed"]}, "destinations": ["expo:oncall"]}]}"#,
+    )
+    .unwrap();
+    let mut endpoint = WebhookEndpoint::new("deploys");
+    endpoint.devices = vec!["ops".to_string()];
+    state.endpoints = vec![Arc::new(endpoint)];
+    let state = Arc::new(state);
+    let url = common::serve(
+        Router::new()
+            .route(
+                "/admin/groups/{name}",
+                put(admin::update_device_group).delete(admin::delete_device_group),
+            )
+            .with_state(state.clone()),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!("{}/admin/groups/oncall", url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Device group is still used by ROUTING_RULES"
+    );
+    let response = client
+        .put(format!("{}/admin/groups/ops", url))
+        .header("Content-Type", "application/json")
+        .body(r#"{"members": []}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Device group is still used by endpoint deploys"
+    );
+    assert!(state.expo.groups.get("oncall").is_some());
+    assert!(state.expo.groups.get("ops").is_some());
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::{env, sync::Arc, time::Duration};
+
+use axum::{Router, routing::get};
+use coolify_expo_notification_relay::{
+    ExpoService, Notification, NotificationSink, WebhookRepeaterService,
+    services::{health, repeater::RepeaterTarget, retry::RetryPolicy},
+};
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn ready_reports_every_check() {
+    let (state, _receiver) = common::app_state();
+    let url = common::serve(
+        Router::new()
+            .route("/health", get(health::show_health))
+            .route("/ready", get(health::show_ready))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/health", url)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "OK");
+
+    for path in ["/ready", "/health?verbose"] {
+        let response = client.get(format!("{}{}", url, path)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let report: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(report["status"], "ok");
+        assert_eq!(report["checks"]["database"]["status"], "ok");
+        assert_eq!(report["checks"]["poller"]["status"], "disabled");
+        assert_eq!(report["checks"]["repeaters"]["status"], "disabled");
+    }
+}
+
+#[tokio::test]
+async fn a_failed_push_does_not_fail_readiness() {
+    let (mut state, _receiver) = common::app_state();
+    // Nothing listens on the discard port, so the push fails.
+    state.expo = Arc::new(ExpoService::new(
+        vec!["ExponentPushToken[abc123xyz]".to_string()],
+        "http://127.0.0.1:9".to_string(),
+        reqwest::Client::new(),
+    ));
+    let notification = Notification {
+        title: "Backup Failed".to_string(),
+        body: "Backup failed".to_string(),
+    };
+    state
+        .expo
+        .deliver(&json!({ "event": "backup_failed" }), &notification)
+        .await;
+    let url = common::serve(
+        Router::new()
+            .route("/ready", get(health::show_ready))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let response = reqwest::get(format!("{}/ready", url)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let report: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(report["status"], "ok");
+    assert_eq!(report["checks"]["expo"]["status"], "degraded");
+    assert_eq!(report["checks"]["expo"]["critical"], false);
+}
+
+#[tokio::test]
+async fn ready_lists_repeaters_without_their_urls() {
+    let (mut state, _receiver) = common::app_state();
+    let mut named = RepeaterTarget::new("http://127.0.0.1:9/hook?token=secret");
+    named.name = Some("archive".to_string());
+    let mut repeater = WebhookRepeaterService::new(
+        vec![
+            named,
+            RepeaterTarget::new("http://127.0.0.1:9/other?token=secret"),
+        ],
+        reqwest::Client::new(),
+    );
+    repeater.retry_policy = RetryPolicy::new(1, Duration::from_millis(1));
+    let notification = Notification {
+        title: "Backup Failed".to_string(),
+        body: "Backup failed".to_string(),
+    };
+    repeater
+        .forward(&json!({ "event": "backup_failed" }), &notification)
+        .await
+        .unwrap();
+    state.repeater = Arc::new(repeater);
+    let url = common::serve(
+        Router::new()
+            .route("/ready", get(health::show_ready))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let body = reqwest::get(format!("{}/ready", url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(!body.contains("secret"));
+    let report: Value = serde_json::from_str(&body).unwrap();
+    let repeaters = &report["checks"]["repeaters"];
+    assert_eq!(repeaters["status"], "degraded");
+    assert_eq!(repeaters["details"][0]["target"], "archive");
+    assert_eq!(repeaters["details"][1]["target"], "1");
+}
+
+#[tokio::test]
+async fn pushes_that_keep_failing_fail_readiness() {
+    unsafe {
+        env::set_var("READY_EXPO_MAX_FAILURE_SECONDS", "0");
+    }
+    let (mut state, _receiver) = common::app_state();
+    state.expo = Arc::new(ExpoService::new(
+        vec![
+            "ExponentPushToken[abc123xyz]".to_string(),
+            "ExponentPushToken[def456uvw]".to_string(),
+        ],
+        "http://127.0.0.1:9".to_string(),
+        reqwest::Client::new(),
+    ));
+    let notification = Notification {
+        title: "Backup Failed".to_string(),
+        body: "Backup failed".to_string(),
+    };
+    state
+        .expo
+        .deliver(&json!({ "event": "backup_failed" }), &notification)
+        .await;
+    let url = common::serve(
+        Router::new()
+            .route("/ready", get(health::show_ready))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let response = reqwest::get(format!("{}/ready", url)).await.unwrap();
+    assert_eq!(response.status(), 503);
+    let report: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(report["checks"]["expo"]["critical"], true);
+    assert_eq!(
+        report["checks"]["expo"]["details"]["consecutive_failures"],
+        2
+    );
+}
@@ -0,0 +1,86 @@
+use coolify_expo_notification_relay::{
+    HistoryStore, Notification, WebhookPayload,
+    services::{
+        expo::ExpoDelivery,
+        history::{DeliveryStatus, EventQuery},
+    },
+};
+use serde_json::json;
+
+fn record(history: &HistoryStore, payload: serde_json::Value) -> i64 {
+    let webhook_payload = WebhookPayload::from_value(payload.clone()).unwrap();
+    let notification = Notification {
+        title: "Title".to_string(),
+        body: "Body".to_string(),
+    };
+
+    history
+        .record_event(&payload, &webhook_payload, &notification)
+        .unwrap()
+}
+
+fn delivery(success: bool) -> ExpoDelivery {
+    ExpoDelivery {
+        token: "ExponentPushToken[abc123xyz]".to_string(),
+        success,
+        detail: None,
+    }
+}
+
+#[test]
+fn recorded_event_starts_pending_and_stores_delivery_outcome() {
+    let history = HistoryStore::open(":memory:", 30).unwrap();
+    let id = record(&history, json!({ "event": "test" }));
+
+    let event = history.get(id).unwrap().unwrap();
+    assert_eq!(event.delivery_status, DeliveryStatus::Pending);
+
+    history
+        .record_deliveries(
+            id,
+            DeliveryStatus::Partial,
+            &[delivery(true), delivery(false)],
+        )
+        .unwrap();
+
+    let event = history.get(id).unwrap().unwrap();
+    assert_eq!(event.delivery_status, DeliveryStatus::Partial);
+    assert_eq!(event.deliveries.len(), 2);
+    assert_eq!(event.payload, json!({ "event": "test" }));
+}
+
+#[test]
+fn query_filters_and_paginates_events() {
+    let history = HistoryStore::open(":memory:", 30).unwrap();
+    for _ in 0..3 {
+        record(
+            &history,
+            json!({ "event": "deployment_success", "application_name": "web" }),
+        );
+    }
+    let failed = record(
+        &history,
+        json!({ "event": "backup_failed", "server_name": "prod" }),
+    );
+    history.record_deliveries(failed, DeliveryStatus::Failed, &[delivery(false)]).unwrap();
+
+    let page = history
+        .query(&EventQuery {
+            app: Some("web".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.events.len(), 2);
+
+    let page = history
+        .query(&EventQuery {
+            status: Some(DeliveryStatus::Failed),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.events[0].id, failed);
+    assert_eq!(page.events[0].server_name.as_deref(), Some("prod"));
+}
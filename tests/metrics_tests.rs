@@ -0,0 +1,69 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State};
+use coolify_expo_notification_relay::{WebhookPayload, event_parser, metrics, services};
+use serde_json::json;
+
+#[test]
+fn unknown_events_share_one_label() {
+    assert_eq!(
+        metrics::event_label(Some("deployment_failed")),
+        "deployment_failed"
+    );
+    assert_eq!(metrics::event_label(Some("made_up_event")), "unknown");
+    assert_eq!(metrics::event_label(None), "unknown");
+}
+
+#[tokio::test]
+async fn parse_fallbacks_are_counted_for_received_webhooks() {
+    let (state, mut receiver) = common::app_state();
+    let fallbacks = || {
+        metrics::PARSE_FALLBACKS
+            .with_label_values(&["unknown"])
+            .get()
+    };
+    let before = fallbacks();
+
+    // Rendering alone, e.g. for a route dry run, is not counted.
+    event_parser::parse_event(&WebhookPayload {
+        event: Some("made_up_event".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(fallbacks(), before);
+
+    services::handle_webhook(
+        State(Arc::new(state)),
+        Json(json!({ "event": "made_up_event" })),
+    )
+    .await;
+    receiver.recv().await.unwrap();
+
+    assert_eq!(fallbacks(), before + 1);
+    let rendered = metrics::render().unwrap();
+    assert!(rendered.contains("relay_parse_fallbacks_total{event=\"unknown\"}"));
+    assert!(rendered.contains("relay_outbox_depth"));
+}
+
+#[tokio::test]
+async fn status_changes_without_their_own_notification_are_parse_fallbacks() {
+    let fallbacks = || {
+        metrics::PARSE_FALLBACKS
+            .with_label_values(&["status_changed"])
+            .get()
+    };
+    let before = fallbacks();
+
+    for title in ["Application stopped", "Application restarted"] {
+        let (state, mut receiver) = common::app_state();
+        services::handle_webhook(
+            State(Arc::new(state)),
+            Json(json!({ "event": "status_changed", "title": title })),
+        )
+        .await;
+        receiver.recv().await.unwrap();
+    }
+
+    assert_eq!(fallbacks(), before + 1);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use axum::http::StatusCode;
+use coolify_expo_notification_relay::{Notification, services::ntfy::NtfyService};
+use serde_json::json;
+
+#[tokio::test]
+async fn critical_event_is_published_with_urgent_priority_and_actions() {
+    let (url, received) = common::mock("/", |_| StatusCode::OK).await;
+
+    let mut ntfy = NtfyService::new(
+        format!("{}/", url),
+        "coolify".to_string(),
+        reqwest::Client::new(),
+    );
+    ntfy.access_token = Some("tk_secret".to_string());
+    let notification = Notification {
+        title: "Deployment Failed".to_string(),
+        body: "web failed to deploy".to_string(),
+    };
+
+    ntfy.send_notification(
+        &json!({
+            "event": "deployment_failed",
+            "deployment_url": "https://coolify.example/deployment/1",
+            "fqdn": "web.example.com,www.example.com",
+        }),
+        &notification,
+    )
+    .await
+    .unwrap();
+
+    let request = received.lock().unwrap().pop().unwrap();
+    let message = request.json();
+    assert_eq!(request.headers["authorization"], "Bearer tk_secret");
+    assert_eq!(message["topic"], "coolify");
+    assert_eq!(message["priority"], 5);
+    assert_eq!(
+        message["tags"],
+        json!(["rotating_light", "deployment_failed"])
+    );
+    assert_eq!(message["click"], "https://coolify.example/deployment/1");
+    assert_eq!(message["actions"][1]["url"], "https://web.example.com");
+}
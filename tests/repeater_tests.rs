@@ -0,0 +1,311 @@
+mod common;
+
+use std::{env, fs, time::Duration};
+
+use axum::{
+    Router,
+    http::{Method, StatusCode, header},
+    response::IntoResponse,
+    routing::post,
+};
+use coolify_expo_notification_relay::{
+    HistoryStore, Notification, WebhookRepeaterService, metrics,
+    services::{repeater::RepeaterTarget, retry::RetryPolicy},
+};
+use serde_json::json;
+
+fn notification() -> Notification {
+    Notification {
+        title: "Backup failed".to_string(),
+        body: "Backup of db failed.".to_string(),
+    }
+}
+
+/// Starts a target that fails the first `failures` requests with `500`.
+async fn flaky_target(failures: usize) -> (String, common::Requests) {
+    let (url, hits) = common::mock("/", move |count| {
+        if count <= failures {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    })
+    .await;
+
+    (format!("{}/", url), hits)
+}
+
+fn repeater(urls: Vec<String>) -> WebhookRepeaterService {
+    let targets = urls.into_iter().map(RepeaterTarget::new).collect();
+    let mut repeater = WebhookRepeaterService::new(targets, reqwest::Client::new());
+    repeater.retry_policy = RetryPolicy::new(3, Duration::from_millis(1));
+    repeater
+}
+
+#[test]
+fn retry_delay_grows_exponentially_up_to_the_ceiling() {
+    let policy = RetryPolicy {
+        max_attempts: 10,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(5),
+    };
+
+    assert_eq!(policy.delay_for(1), Duration::from_secs(1));
+    assert_eq!(policy.delay_for(2), Duration::from_secs(2));
+    assert_eq!(policy.delay_for(3), Duration::from_secs(4));
+    assert_eq!(policy.delay_for(4), Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn failed_forward_is_retried_until_it_succeeds() {
+    let (url, hits) = flaky_target(2).await;
+
+    let outcomes = repeater(vec![url])
+        .forward(&json!({ "event": "test" }), &notification())
+        .await
+        .unwrap();
+
+    assert!(outcomes[0].success);
+    assert_eq!(outcomes[0].attempts, 3);
+    assert_eq!(hits.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn exhausted_forward_can_be_dead_lettered_and_replayed() {
+    let (url, _) = flaky_target(3).await;
+    let repeater = repeater(vec![url.clone()]);
+    let history = HistoryStore::open(":memory:", 30).unwrap();
+    let payload = json!({ "event": "backup_failed" });
+
+    let outcomes = repeater.forward(&payload, &notification()).await.unwrap();
+    assert!(!outcomes[0].success);
+    assert_eq!(outcomes[0].attempts, 3);
+
+    let id = history.record_dead_letter(&payload, &outcomes[0]).unwrap();
+    let dead_letter = history.get_dead_letter(id).unwrap().unwrap();
+    assert_eq!(dead_letter.target_url, url);
+    assert_eq!(dead_letter.payload, payload);
+
+    let outcome = repeater
+        .forward_to(
+            &dead_letter.target_url,
+            &dead_letter.payload,
+            &notification(),
+        )
+        .await
+        .unwrap();
+    assert!(outcome.success);
+
+    history.record_replay(id, &outcome).unwrap();
+    let queued = history.dead_letters(&Default::default()).unwrap();
+    assert!(
+        queued.is_empty(),
+        "replayed dead letters should leave the queue"
+    );
+}
+
+#[tokio::test]
+async fn invalid_retry_after_headers_fall_back_to_the_backoff() {
+    let (url, hits) = common::mock("/", |count| {
+        let retry_after = match count {
+            1 => "-1",
+            2 => "inf",
+            3 => "1e300",
+            _ => return StatusCode::OK.into_response(),
+        };
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, retry_after)],
+        )
+            .into_response()
+    })
+    .await;
+    let mut repeater = repeater(vec![format!("{}/", url)]);
+    repeater.retry_policy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(50),
+    };
+
+    let outcomes = repeater
+        .forward(&json!({ "event": "test" }), &notification())
+        .await
+        .unwrap();
+
+    assert!(outcomes[0].success);
+    assert_eq!(hits.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn slow_target_times_out_without_delaying_the_others() {
+    let slow_url = common::serve(Router::new().route(
+        "/",
+        post(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            StatusCode::OK
+        }),
+    ))
+    .await;
+    let (fast_url, _) = flaky_target(0).await;
+
+    let mut repeater = repeater(vec![slow_url, fast_url]);
+    repeater.retry_policy = RetryPolicy::new(1, Duration::from_millis(1));
+    repeater.timeout = Duration::from_millis(200);
+
+    let outcomes = repeater
+        .forward(&json!({ "event": "test" }), &notification())
+        .await
+        .unwrap();
+
+    assert!(!outcomes[0].success);
+    assert!(outcomes[0].duration_ms < 2000);
+    assert!(outcomes[1].success);
+    assert!(outcomes[1].duration_ms < outcomes[0].duration_ms);
+}
+
+#[tokio::test]
+async fn target_settings_are_applied_to_the_request() {
+    let (url, captured) = common::mock("/", |_| StatusCode::OK).await;
+
+    let targets = WebhookRepeaterService::parse_targets(&format!(
+        r#"[{{
+            "url": "{url}/",
+            "method": "put",
+            "headers": {{ "X-Environment": "production" }},
+            "auth": {{ "type": "api_key", "key": "k3y" }},
+            "template": {{
+                "text": "{{{{title}}}} on {{{{payload.server_name}}}}",
+                "details": {{ "body": "{{{{body}}}}", "count": "{{{{payload.count}}}}" }}
+            }}
+        }}]"#
+    ))
+    .unwrap();
+    let outcomes = WebhookRepeaterService::new(targets, reqwest::Client::new())
+        .forward(
+            &json!({ "event": "backup_failed", "server_name": "prod", "count": 2 }),
+            &notification(),
+        )
+        .await
+        .unwrap();
+    assert!(outcomes[0].success);
+
+    let request = captured.lock().unwrap().pop().unwrap();
+    assert_eq!(request.method, Method::PUT);
+    assert_eq!(request.headers["x-environment"], "production");
+    assert_eq!(request.headers["x-api-key"], "k3y");
+    assert_eq!(
+        request.json(),
+        json!({
+            "text": "Backup failed on prod",
+            "details": { "body": "Backup of db failed.", "count": 2 }
+        })
+    );
+}
+
+#[test]
+fn invalid_target_definitions_are_rejected() {
+    assert!(
+        WebhookRepeaterService::parse_targets(
+            r#"[{ "url": "http://a", "method": "NOT A METHOD" }]"#
+        )
+        .is_err()
+    );
+    assert!(
+        WebhookRepeaterService::parse_targets(
+            r#"[{ "url": "http://a", "auth": { "type": "bearer" } }]"#
+        )
+        .is_err()
+    );
+    assert!(
+        WebhookRepeaterService::parse_targets(
+            r#"[{ "url": "http://a", "tls": { "ca_cert": "/nonexistent/ca.pem" } }]"#
+        )
+        .is_err()
+    );
+    let ca_cert = env::temp_dir().join("repeater-tests-invalid-ca.pem");
+    fs::write(&ca_cert, "not a certificate").unwrap();
+    assert!(
+        WebhookRepeaterService::parse_targets(&format!(
+            r#"[{{ "url": "http://a", "tls": {{ "ca_cert": "{}" }} }}]"#,
+            ca_cert.display()
+        ))
+        .is_err()
+    );
+    assert!(
+        WebhookRepeaterService::parse_targets("")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn filtered_out_targets_receive_nothing() {
+    let (failures_url, failures_hits) = flaky_target(0).await;
+    let (deployments_url, deployments_hits) = flaky_target(0).await;
+    let targets = WebhookRepeaterService::parse_targets(&format!(
+        r#"[
+            {{ "url": "{failures_url}", "filter": {{ "include": {{ "events": ["*_failed"] }} }} }},
+            {{ "url": "{deployments_url}", "filter": {{ "include": {{ "events": ["deployment_*"] }} }} }}
+        ]"#
+    ))
+    .unwrap();
+    let repeater = WebhookRepeaterService::new(targets, reqwest::Client::new());
+
+    let outcomes = repeater
+        .forward(&json!({ "event": "backup_failed" }), &notification())
+        .await
+        .unwrap();
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].url, failures_url);
+    assert_eq!(failures_hits.lock().unwrap().len(), 1);
+    assert_eq!(deployments_hits.lock().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn named_forwards_only_reach_the_selected_targets() {
+    let (audit_url, audit_hits) = flaky_target(0).await;
+    let (other_url, other_hits) = flaky_target(0).await;
+    let targets = vec![
+        RepeaterTarget {
+            name: Some("audit".to_string()),
+            ..RepeaterTarget::new(audit_url)
+        },
+        RepeaterTarget::new(other_url),
+    ];
+    let repeater = WebhookRepeaterService::new(targets, reqwest::Client::new());
+
+    let outcomes = repeater
+        .forward_named(
+            &json!({ "event": "backup_failed" }),
+            &notification(),
+            &["audit".to_string()],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(audit_hits.lock().unwrap().len(), 1);
+    assert_eq!(other_hits.lock().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn metrics_label_targets_without_their_urls() {
+    let (url, _hits) = flaky_target(0).await;
+    let mut named = RepeaterTarget::new(format!("{}?token=named-s3cret", url));
+    named.name = Some("audit".to_string());
+    let targets = vec![RepeaterTarget::new(format!("{}?token=s3cret", url)), named];
+    let repeater = WebhookRepeaterService::new(targets, reqwest::Client::new());
+
+    repeater
+        .forward(&json!({ "event": "backup_failed" }), &notification())
+        .await
+        .unwrap();
+
+    let rendered = metrics::render().unwrap();
+    assert!(!rendered.contains("s3cret"));
+    assert!(rendered.contains(r#"relay_repeater_forwards_total{outcome="success",target="0"}"#));
+    assert!(
+        rendered.contains(r#"relay_repeater_forwards_total{outcome="success",target="audit"}"#)
+    );
+}
@@ -0,0 +1,59 @@
+use coolify_expo_notification_relay::{Notification, routing::Routes};
+use serde_json::json;
+
+fn notification() -> Notification {
+    Notification {
+        title: "Title".to_string(),
+        body: "Body".to_string(),
+    }
+}
+
+const RULES: &str = r#"{
+    "rules": [
+        { "name": "critical", "match": { "severity": ["critical"] }, "destinations": ["slack", "expo"], "stop": true },
+        { "name": "shop", "match": { "project": ["shop"], "message": "(?i)timeout" }, "destinations": ["repeater:audit"] },
+        { "match": { "events": ["deployment_*"] }, "destinations": ["discord"] }
+    ],
+    "default": ["expo"]
+}"#;
+
+#[test]
+fn rules_are_evaluated_in_order_until_one_stops() {
+    let routes = Routes::parse(RULES).unwrap();
+
+    let route = routes.route(
+        &json!({ "event": "deployment_failed", "project": "shop", "message": "Timeout" }),
+        &notification(),
+    );
+    assert_eq!(route.rules, vec!["critical"]);
+    assert_eq!(route.destinations, vec!["slack", "expo"]);
+
+    let route = routes.route(
+        &json!({ "event": "deployment_success", "project": "shop", "message": "Request timeout" }),
+        &notification(),
+    );
+    assert_eq!(route.rules, vec!["shop", "#3"]);
+    assert_eq!(route.destinations, vec!["repeater:audit", "discord"]);
+    assert_eq!(route.targets("repeater"), Some(vec!["audit".to_string()]));
+    assert_eq!(route.targets("discord"), Some(Vec::new()));
+    assert_eq!(route.targets("slack"), None);
+}
+
+#[test]
+fn unmatched_events_take_the_default_route() {
+    let routes = Routes::parse(RULES).unwrap();
+    let route = routes.route(&json!({ "event": "backup_success" }), &notification());
+    assert!(route.default);
+    assert_eq!(route.destinations, vec!["expo"]);
+
+    // Without rules every output receives every event.
+    let route = Routes::default().route(&json!({ "event": "backup_success" }), &notification());
+    assert!(route.default);
+    assert_eq!(route.targets("telegram"), Some(Vec::new()));
+
+    assert!(
+        Routes::parse(r#"{"rules": [{"match": {"message": "("}, "destinations": ["expo"]}]}"#)
+            .is_err()
+    );
+    assert!(Routes::parse(r#"{"rules": [{"destinations": []}]}"#).is_err());
+}
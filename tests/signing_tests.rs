@@ -0,0 +1,71 @@
+mod common;
+
+use axum::http::StatusCode;
+use coolify_expo_notification_relay::{
+    Notification, WebhookRepeaterService,
+    services::repeater::RepeaterTarget,
+    signing::{self, DEFAULT_TOLERANCE_SECS},
+    verify_signature,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn signed_forward_can_be_verified_by_the_receiver() {
+    let (url, captured) = common::mock("/", |_| StatusCode::OK).await;
+
+    let target = RepeaterTarget {
+        signing_secret: Some("s3cret".to_string()),
+        ..RepeaterTarget::new(format!("{}/", url))
+    };
+    let repeater = WebhookRepeaterService::new(vec![target], reqwest::Client::new());
+    let outcomes = repeater
+        .forward(
+            &json!({ "event": "deployment_success" }),
+            &Notification {
+                title: "Deployed".to_string(),
+                body: "Deployed app.".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(outcomes[0].success);
+
+    let common::Received { headers, body, .. } = captured.lock().unwrap().pop().unwrap();
+    let timestamp = headers[signing::TIMESTAMP_HEADER].to_str().unwrap();
+    let signature = headers[signing::SIGNATURE_HEADER].to_str().unwrap();
+
+    assert_eq!(
+        verify_signature(
+            "s3cret",
+            timestamp,
+            signature,
+            &body,
+            DEFAULT_TOLERANCE_SECS
+        ),
+        Ok(())
+    );
+    assert!(
+        verify_signature("wrong", timestamp, signature, &body, DEFAULT_TOLERANCE_SECS).is_err()
+    );
+    assert!(
+        verify_signature(
+            "s3cret",
+            timestamp,
+            signature,
+            b"{}",
+            DEFAULT_TOLERANCE_SECS
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn stale_timestamps_are_rejected() {
+    let body = br#"{"event":"test"}"#;
+    let signature = signing::sign("s3cret", 1_000, body);
+
+    assert_eq!(
+        verify_signature("s3cret", "1000", &signature, body, DEFAULT_TOLERANCE_SECS),
+        Err("Signature timestamp is outside the allowed tolerance.")
+    );
+}
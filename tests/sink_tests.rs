@@ -0,0 +1,100 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+use coolify_expo_notification_relay::{
+    Notification, NotificationSink,
+    services::{self, history::DeliveryStatus, sink::SinkReport, slack::SlackService},
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn webhooks_are_delivered_to_every_sink() {
+    let (state, mut receiver) = common::app_state();
+    let url = common::serve(
+        Router::new()
+            .route("/webhook", post(services::handle_webhook))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/webhook", url))
+        .header("Content-Type", "application/json")
+        .body(r#"{"event":"test"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let (payload, notification) = receiver.recv().await.unwrap();
+    assert_eq!(payload["event"], "test");
+    assert!(!notification.title.is_empty());
+}
+
+#[test]
+fn delivery_status_covers_every_selected_sink() {
+    assert_eq!(SinkReport::default().status(), DeliveryStatus::Skipped);
+
+    // A route without push devices still delivers to chat.
+    let mut report = SinkReport::from_deliveries(Vec::new());
+    report.merge(SinkReport::from_result(Ok(true)));
+    assert_eq!(report.status(), DeliveryStatus::Delivered);
+
+    report.merge(SinkReport::from_result(Err("Slack is down".to_string())));
+    assert_eq!(report.status(), DeliveryStatus::Partial);
+
+    let report = SinkReport::from_result(Err("Slack is down".to_string()));
+    assert_eq!(report.status(), DeliveryStatus::Failed);
+}
+
+#[tokio::test]
+async fn events_filtered_out_by_a_sink_are_not_counted_as_sent() {
+    let (url, received) = common::mock("/", |_| "ok").await;
+    let mut slack = SlackService::new(format!("{}/", url), reqwest::Client::new());
+    slack.filter =
+        serde_json::from_value(json!({ "include": { "events": ["*_failed"] } })).unwrap();
+    let notification = Notification {
+        title: "Backup Success".to_string(),
+        body: "Backup done".to_string(),
+    };
+
+    let report = slack
+        .deliver(&json!({ "event": "backup_success" }), &notification)
+        .await;
+
+    assert_eq!((report.sent, report.failed), (0, 0));
+    assert_eq!(report.status(), DeliveryStatus::Skipped);
+    assert!(received.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn every_webhook_gets_its_own_correlation_id() {
+    let (state, mut receiver) = common::app_state();
+    let url = common::serve(
+        Router::new()
+            .route("/webhook", post(services::handle_webhook))
+            .with_state(Arc::new(state)),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let response = client
+            .post(format!("{}/webhook", url))
+            .header("Content-Type", "application/json")
+            .body(r#"{"event":"test"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+        let id = response.headers()["x-correlation-id"].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok());
+        ids.push(id.to_string());
+        receiver.recv().await.unwrap();
+    }
+
+    assert_ne!(ids[0], ids[1]);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::time::Duration;
+
+use coolify_expo_notification_relay::{
+    Notification,
+    services::{retry::RetryPolicy, slack::SlackService},
+};
+use serde_json::json;
+
+fn slack(url: String) -> SlackService {
+    let mut slack = SlackService::new(format!("{}/", url), reqwest::Client::new());
+    slack.retry_policy = RetryPolicy::new(1, Duration::from_millis(1));
+    slack
+}
+
+#[tokio::test]
+async fn failure_is_posted_as_a_red_block_kit_message() {
+    let (url, received) = common::mock("/", |_| "ok").await;
+    let notification = Notification {
+        title: "Deployment Failed".to_string(),
+        body: "shop <main> failed to deploy".to_string(),
+    };
+
+    slack(url)
+        .send_notification(
+            &json!({
+                "event": "deployment_failed",
+                "server_name": "prod",
+                "project": "shop",
+                "application_name": "web",
+                "deployment_url": "https://coolify.example/deployment/1",
+            }),
+            &notification,
+        )
+        .await
+        .unwrap();
+
+    let message = received.lock().unwrap().pop().unwrap().json();
+    let attachment = &message["attachments"][0];
+    let blocks = attachment["blocks"].as_array().unwrap();
+
+    assert_eq!(attachment["color"], "#e01e5a");
+    assert_eq!(
+        blocks[0]["text"]["text"],
+        ":rotating_light: Deployment Failed"
+    );
+    assert_eq!(
+        blocks[1]["text"]["text"],
+        "shop &lt;main&gt; failed to deploy"
+    );
+    assert_eq!(blocks[2]["fields"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        blocks[3]["elements"][0]["url"],
+        "https://coolify.example/deployment/1"
+    );
+}
+
+#[tokio::test]
+async fn filtered_events_are_not_posted() {
+    let (url, received) = common::mock("/", |_| "ok").await;
+    let mut slack = slack(url);
+    slack.filter =
+        serde_json::from_value(json!({ "include": { "events": ["*_failed"] } })).unwrap();
+    let notification = Notification {
+        title: "Backup Success".to_string(),
+        body: "Backup done".to_string(),
+    };
+
+    let sent = slack
+        .send_notification(&json!({ "event": "backup_success" }), &notification)
+        .await
+        .unwrap();
+
+    assert!(!sent);
+    assert!(received.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn transport_errors_do_not_reveal_the_webhook_url() {
+    let slack = slack("http://127.0.0.1:9/services/T000/B000/s3cret".to_string());
+    let notification = Notification {
+        title: "Backup Failed".to_string(),
+        body: "Backup failed".to_string(),
+    };
+
+    let error = slack
+        .send_notification(&json!({ "event": "backup_failed" }), &notification)
+        .await
+        .unwrap_err();
+
+    assert!(!error.contains("s3cret"), "{}", error);
+}
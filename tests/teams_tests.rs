@@ -0,0 +1,52 @@
+mod common;
+
+use axum::http::StatusCode;
+use coolify_expo_notification_relay::{Notification, services::teams::TeamsService};
+use serde_json::json;
+
+#[tokio::test]
+async fn adaptive_card_has_facts_and_link_actions() {
+    let (url, received) = common::mock("/", |_| StatusCode::ACCEPTED).await;
+
+    let notification = Notification {
+        title: "Container Stopped".to_string(),
+        body: "Container web stopped".to_string(),
+    };
+    TeamsService::new(format!("{}/", url), reqwest::Client::new())
+        .send_notification(
+            &json!({
+                "event": "container_stopped",
+                "server_name": "prod",
+                "container_name": "web",
+                "url": "https://coolify.example/server/1",
+            }),
+            &notification,
+        )
+        .await
+        .unwrap();
+
+    let message = received.lock().unwrap().pop().unwrap().json();
+    let attachment = &message["attachments"][0];
+    let card = &attachment["content"];
+
+    assert_eq!(message["type"], "message");
+    assert_eq!(
+        attachment["contentType"],
+        "application/vnd.microsoft.card.adaptive"
+    );
+    assert_eq!(card["body"][0]["text"], "Container Stopped");
+    assert_eq!(card["body"][0]["color"], "Attention");
+    assert_eq!(
+        card["body"][2]["facts"],
+        json!([
+            { "title": "Event", "value": "container_stopped" },
+            { "title": "Server", "value": "prod" },
+            { "title": "Container", "value": "web" },
+        ])
+    );
+    assert_eq!(card["actions"][0]["type"], "Action.OpenUrl");
+    assert_eq!(
+        card["actions"][0]["url"],
+        "https://coolify.example/server/1"
+    );
+}
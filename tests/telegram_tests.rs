@@ -0,0 +1,80 @@
+mod common;
+
+use std::env;
+
+use axum::Json;
+use coolify_expo_notification_relay::{
+    Notification,
+    services::telegram::{TelegramChat, TelegramService, escape_markdown},
+};
+use serde_json::json;
+
+#[test]
+fn markdown_v2_special_characters_are_escaped() {
+    assert_eq!(
+        escape_markdown("v1.2.3 (beta) - 100% done!"),
+        r"v1\.2\.3 \(beta\) \- 100% done\!"
+    );
+    assert_eq!(escape_markdown(r"a_b*c\d"), r"a\_b\*c\\d");
+}
+
+#[tokio::test]
+async fn notification_is_sent_to_every_matching_chat() {
+    let (api_url, received) =
+        common::mock("/bot123:abc/sendMessage", |_| Json(json!({ "ok": true }))).await;
+
+    let chats: Vec<TelegramChat> = serde_json::from_value(json!([
+        { "chat_id": -1001 },
+        { "chat_id": "@alerts", "filter": { "include": { "events": ["*_failed"] } } }
+    ]))
+    .unwrap();
+    let telegram = TelegramService::new(
+        api_url,
+        "123:abc".to_string(),
+        chats,
+        reqwest::Client::new(),
+    );
+    let notification = Notification {
+        title: "Deployment Success".to_string(),
+        body: "web.app was deployed".to_string(),
+    };
+
+    telegram
+        .send_notification(
+            &json!({ "event": "deployment_success", "fqdn": "web.example.com" }),
+            &notification,
+        )
+        .await
+        .unwrap();
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    let message = received[0].json();
+    assert_eq!(message["chat_id"], "-1001");
+    assert_eq!(message["parse_mode"], "MarkdownV2");
+    assert_eq!(
+        message["text"],
+        "*Deployment Success*\n\nweb\\.app was deployed"
+    );
+    assert_eq!(
+        message["reply_markup"]["inline_keyboard"][0][0]["url"],
+        "https://web.example.com"
+    );
+}
+
+#[test]
+fn invalid_chats_in_the_environment_are_rejected() {
+    unsafe {
+        env::set_var("TELEGRAM_BOT_TOKEN", "123:abc");
+        env::set_var("TELEGRAM_CHATS", r#"[{"id": -1001}]"#);
+    }
+
+    let result = TelegramService::from_env(reqwest::Client::new());
+
+    unsafe {
+        env::remove_var("TELEGRAM_BOT_TOKEN");
+        env::remove_var("TELEGRAM_CHATS");
+    }
+    let error = result.err().unwrap();
+    assert!(error.starts_with("Invalid TELEGRAM_CHATS"), "{}", error);
+}